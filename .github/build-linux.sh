@@ -10,6 +10,9 @@ sudo apt-get install -y build-essential make cmake pkg-config llvm libclang-dev
 
 # build binaries
 cargo build --all --release
+
+# ensure bundled shaders compile with the default shader frontend
+make check-shaders || exit 1
 strip "$RELEASE"
 
 # build project structure
@@ -17,6 +20,7 @@ mkdir -p "$PACKAGE/bin"
 cp README.md "$PACKAGE/."
 cp LICENSE "$PACKAGE/."
 cp -r shaders "$PACKAGE/."
+cp -r pam "$PACKAGE/."
 cp default-config.yaml "$PACKAGE/config.yaml"
 mv $RELEASE "$PACKAGE/bin/."
 cp .github/install-linux.sh "$PACKAGE/install.sh"
@@ -6,6 +6,7 @@ CONFIG="$HOME/.config/$NAME"
 
 cd $(dirname $SELF)
 sudo cp -vf bin/* /usr/local/bin/.
+sudo install -vm644 pam/dynlock /etc/pam.d/dynlock
 mkdir -p "$CONFIG"
 cp -vfr shaders "$CONFIG/."
 cp -vf config.yaml "$CONFIG/."
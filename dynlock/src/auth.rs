@@ -0,0 +1,462 @@
+//! PAM Authentication and Conversation Handling
+use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Result};
+
+use dynlock_lib::AuthConfig;
+
+/// Directories Searched by PAM for Service Configurations
+const PAM_DIRS: [&str; 2] = ["/etc/pam.d", "/usr/lib/pam.d"];
+
+/// Ensure the Configured PAM Service is Installed
+///
+/// PAM silently handles unknown services with its `other` service,
+/// which usually denies everything and locks the user out.
+fn pam_service(service: &str) -> Result<&str> {
+    let installed = PAM_DIRS
+        .iter()
+        .any(|dir| Path::new(dir).join(service).exists());
+    match installed {
+        true => Ok(service),
+        false => Err(anyhow!(
+            "pam - service {service:?} is not installed in {}",
+            PAM_DIRS.join(" or ")
+        )),
+    }
+}
+
+/// Authentication Event Reported by PAM Conversation
+#[derive(Debug, Clone)]
+pub enum AuthEvent {
+    /// PAM module requests additional input from the user
+    Prompt { message: String, echo: bool },
+    /// Informational message from a PAM module
+    Info(String),
+    /// Error message from a PAM module
+    Error(String),
+    /// Authentication attempt completed with the given result
+    Finished(bool),
+}
+
+/// PAM Conversation Handler Forwarding Prompts to the UI
+struct Conversation {
+    username: String,
+    password: Option<String>,
+    events: Sender<AuthEvent>,
+    responses: Receiver<String>,
+}
+
+impl Conversation {
+    /// Forward Prompt to the UI and Block until a Response is Given
+    fn prompt(&mut self, msg: &CStr, echo: bool) -> Result<CString, ()> {
+        // the first secret prompt is answered with the submitted password
+        if !echo {
+            if let Some(password) = self.password.take() {
+                return CString::new(password).map_err(|_| ());
+            }
+        }
+        let message = msg.to_string_lossy().trim().to_owned();
+        log::debug!("pam - prompting user: {message:?}");
+        self.events
+            .send(AuthEvent::Prompt { message, echo })
+            .map_err(|_| ())?;
+        let response = self.responses.recv().map_err(|_| ())?;
+        CString::new(response).map_err(|_| ())
+    }
+}
+
+impl pam::Conversation for Conversation {
+    fn prompt_echo(&mut self, msg: &CStr) -> Result<CString, ()> {
+        self.prompt(msg, true)
+    }
+    fn prompt_blind(&mut self, msg: &CStr) -> Result<CString, ()> {
+        self.prompt(msg, false)
+    }
+    fn info(&mut self, msg: &CStr) {
+        let message = msg.to_string_lossy().trim().to_owned();
+        log::info!("pam - {message}");
+        let _ = self.events.send(AuthEvent::Info(message));
+    }
+    fn error(&mut self, msg: &CStr) {
+        let message = msg.to_string_lossy().trim().to_owned();
+        log::error!("pam - {message}");
+        let _ = self.events.send(AuthEvent::Error(message));
+    }
+    fn username(&self) -> &str {
+        &self.username
+    }
+}
+
+/// Background PAM Authentication Manager
+pub struct Authenticator {
+    service: String,
+    username: String,
+    thread: Option<JoinHandle<()>>,
+    events: Option<Receiver<AuthEvent>>,
+    responses: Option<Sender<String>>,
+}
+
+impl Authenticator {
+    pub fn new(service: &str, username: &str) -> Self {
+        Self {
+            service: service.to_owned(),
+            username: username.to_owned(),
+            thread: None,
+            events: None,
+            responses: None,
+        }
+    }
+
+    /// Check if Authentication Thread is Running
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map(|t| !t.is_finished())
+            .unwrap_or(false)
+    }
+
+    /// Spawn Authentication Thread (if not already running)
+    pub fn start(&mut self, password: String) {
+        // skip authenticating if already in progress
+        if self.is_running() {
+            log::error!("requested auth while already authenticating");
+            return;
+        }
+        let (event_tx, event_rx) = channel();
+        let (response_tx, response_rx) = channel();
+        let service = self.service.to_owned();
+        let conversation = Conversation {
+            username: self.username.to_owned(),
+            password: Some(password),
+            events: event_tx.clone(),
+            responses: response_rx,
+        };
+        // spawn thread to complete login attempt in background
+        self.thread = Some(std::thread::spawn(move || {
+            let result = match pam::Client::with_conversation(&service, conversation) {
+                Ok(mut client) => client.authenticate().is_ok(),
+                Err(err) => {
+                    log::error!("pam - failed to init client for service {service:?}: {err}");
+                    let message = format!("PAM service {service:?} unavailable");
+                    let _ = event_tx.send(AuthEvent::Error(message));
+                    false
+                }
+            };
+            log::info!("pam - authentication complete (success={result})");
+            let _ = event_tx.send(AuthEvent::Finished(result));
+        }));
+        self.events = Some(event_rx);
+        self.responses = Some(response_tx);
+    }
+
+    /// Answer the Pending PAM Prompt
+    pub fn respond(&self, response: String) {
+        if let Some(responses) = self.responses.as_ref() {
+            if responses.send(response).is_err() {
+                log::error!("pam - conversation closed before response");
+            }
+        }
+    }
+
+    /// Abandon the Running Attempt so the Next Input Starts a New One
+    ///
+    /// Closing the channels fails any prompt the PAM thread is blocked on
+    /// and discards whatever it reports afterwards.
+    pub fn cancel(&mut self) {
+        if self.thread.take().is_some() {
+            log::debug!("pam - cancelling pending conversation");
+        }
+        self.events = None;
+        self.responses = None;
+    }
+
+    /// Collect Pending Events from the Authentication Thread
+    pub fn poll(&self) -> Vec<AuthEvent> {
+        self.events
+            .as_ref()
+            .map(|events| events.try_iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Authentication State Shared by the Lock Menus of Every Output
+///
+/// Owns the password buffer and the single in-flight PAM attempt so a
+/// submit is only ever counted once regardless of how many outputs exist.
+pub struct AuthController {
+    auth: Authenticator,
+    password: String,
+    prompt: Option<String>,
+    echo: bool,
+    status: Option<String>,
+    authenticated: bool,
+    typed_at: SystemTime,
+    failures: u32,
+    failed_at: Option<SystemTime>,
+    wrong: bool,
+    backoff: Duration,
+    backoff_max: Duration,
+}
+
+impl AuthController {
+    pub fn new(config: &AuthConfig) -> Result<Self> {
+        let service = pam_service(&config.service)?;
+        log::debug!("pam - using service {service:?}");
+        Ok(Self {
+            auth: Authenticator::new(service, &whoami::username()),
+            password: "".to_owned(),
+            prompt: None,
+            echo: false,
+            status: None,
+            authenticated: false,
+            typed_at: SystemTime::now(),
+            failures: 0,
+            failed_at: None,
+            wrong: false,
+            backoff: config.backoff(),
+            backoff_max: config.backoff_max(),
+        })
+    }
+
+    /// Current Contents of the Password Buffer
+    #[inline]
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// Pending PAM Prompt (if any)
+    #[inline]
+    pub fn prompt(&self) -> Option<&str> {
+        self.prompt.as_deref()
+    }
+
+    /// Check if the Pending Prompt Expects Visible Input
+    #[inline]
+    pub fn echo(&self) -> bool {
+        self.echo
+    }
+
+    /// Check if the Last Attempt Failed and Nothing was Typed Since
+    #[inline]
+    pub fn wrong(&self) -> bool {
+        self.wrong
+    }
+
+    /// Number of Failed Attempts
+    #[inline]
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Time of the Last Failed Attempt
+    #[inline]
+    pub fn failed_at(&self) -> Option<SystemTime> {
+        self.failed_at
+    }
+
+    /// Time of the Last Modification to the Password Buffer
+    #[inline]
+    pub fn typed_at(&self) -> SystemTime {
+        self.typed_at
+    }
+
+    /// Check if Successfully Authenticated
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    /// Check if Authentication is Running without Waiting on User Input
+    #[inline]
+    pub fn is_verifying(&self) -> bool {
+        self.auth.is_running() && self.prompt.is_none()
+    }
+
+    /// Check if Input is Currently Refused
+    pub fn is_waiting(&self) -> bool {
+        self.authenticated || self.is_verifying() || self.backoff_remaining().is_some()
+    }
+
+    /// Delay after the Latest Failure (Doubling up to the Maximum)
+    fn backoff_delay(&self) -> Duration {
+        let exponent = self.failures.saturating_sub(1).min(16);
+        self.backoff
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.backoff_max)
+            .min(self.backoff_max)
+    }
+
+    /// Remaining Delay before Another Attempt is Accepted
+    pub fn backoff_remaining(&self) -> Option<Duration> {
+        let failed_at = self.failed_at?;
+        let elapsed = SystemTime::now()
+            .duration_since(failed_at)
+            .unwrap_or_default();
+        (self.backoff_delay())
+            .checked_sub(elapsed)
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Generate Feedback Line for Current Authentication State
+    fn feedback(&self) -> Option<String> {
+        if self.is_verifying() {
+            return Some("Verifying...".to_owned());
+        }
+        if let Some(remaining) = self.backoff_remaining() {
+            let seconds = remaining.as_secs_f32().ceil();
+            return Some(format!("Too many attempts. Try again in {seconds}s"));
+        }
+        match self.wrong {
+            true => Some(format!(
+                "Wrong password ({} failed attempts)",
+                self.failures
+            )),
+            false => None,
+        }
+    }
+
+    /// Status and Feedback Lines for the Current Authentication State
+    pub fn messages(&self) -> Vec<String> {
+        self.status.iter().cloned().chain(self.feedback()).collect()
+    }
+
+    /// Replace Password Buffer with Edited Input
+    pub fn typing(&mut self, password: String) {
+        self.wrong = false;
+        self.typed_at = SystemTime::now();
+        self.password = password;
+    }
+
+    /// Clear the Password Buffer and Abandon any Pending PAM Prompt
+    pub fn reset(&mut self) {
+        self.typed_at = SystemTime::now();
+        self.password.clear();
+        if self.prompt.take().is_some() {
+            self.echo = false;
+            self.status = None;
+            self.auth.cancel();
+        }
+    }
+
+    /// Submit Buffer as Password or as Response to Pending PAM Prompt
+    pub fn submit(&mut self) {
+        if self.is_waiting() {
+            log::warn!("pam - ignoring submit while waiting on previous attempt");
+            return;
+        }
+        self.wrong = false;
+        let input = std::mem::take(&mut self.password);
+        match self.prompt.take() {
+            Some(_) if self.auth.is_running() => self.auth.respond(input),
+            _ => {
+                self.status = None;
+                self.auth.start(input);
+            }
+        }
+    }
+
+    /// Apply Pending Events from the Authentication Thread
+    pub fn poll(&mut self) {
+        for event in self.auth.poll() {
+            match event {
+                AuthEvent::Prompt { message, echo } => {
+                    self.prompt = Some(message);
+                    self.echo = echo;
+                }
+                AuthEvent::Info(message) | AuthEvent::Error(message) => self.status = Some(message),
+                AuthEvent::Finished(result) => self.finished(result),
+            }
+        }
+    }
+
+    /// Record Result of a Completed Authentication Attempt
+    ///
+    /// Failures are counted towards the backoff until an attempt succeeds.
+    fn finished(&mut self, result: bool) {
+        self.prompt = None;
+        self.echo = false;
+        self.authenticated = result;
+        match result {
+            true => {
+                self.failures = 0;
+                self.failed_at = None;
+                self.wrong = false;
+            }
+            false => {
+                self.failures = self.failures.saturating_add(1);
+                self.failed_at = Some(SystemTime::now());
+                self.wrong = true;
+                self.password.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build Controller without Requiring an Installed PAM Service
+    fn controller(backoff: u64, backoff_max: u64) -> AuthController {
+        AuthController {
+            auth: Authenticator::new("dynlock", "user"),
+            password: "".to_owned(),
+            prompt: None,
+            echo: false,
+            status: None,
+            authenticated: false,
+            typed_at: SystemTime::now(),
+            failures: 0,
+            failed_at: None,
+            wrong: false,
+            backoff: Duration::from_secs(backoff),
+            backoff_max: Duration::from_secs(backoff_max),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_until_capped() {
+        let mut auth = controller(1, 30);
+        assert_eq!(auth.backoff_remaining(), None);
+        let expected = [1, 2, 4, 8, 16, 30, 30];
+        for secs in expected {
+            auth.finished(false);
+            assert_eq!(auth.backoff_delay(), Duration::from_secs(secs));
+        }
+        let remaining = auth.backoff_remaining().expect("missing backoff");
+        assert!(remaining <= Duration::from_secs(30));
+        assert!(auth.is_waiting());
+    }
+
+    #[test]
+    fn backoff_never_overflows() {
+        let mut auth = controller(u64::MAX / 2, u64::MAX);
+        auth.failures = u32::MAX;
+        assert_eq!(auth.backoff_delay(), Duration::from_secs(u64::MAX));
+        auth.finished(false);
+        assert_eq!(auth.failures, u32::MAX);
+        let mut auth = controller(1, 30);
+        auth.failures = u32::MAX;
+        assert_eq!(auth.backoff_delay(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn success_resets_backoff() {
+        let mut auth = controller(1, 30);
+        for _ in 0..3 {
+            auth.finished(false);
+        }
+        assert!(auth.wrong());
+        assert!(auth.backoff_remaining().is_some());
+        auth.finished(true);
+        assert!(auth.is_authenticated());
+        assert!(!auth.wrong());
+        assert_eq!(auth.failures(), 0);
+        assert_eq!(auth.backoff_remaining(), None);
+    }
+}
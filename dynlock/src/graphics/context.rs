@@ -0,0 +1,660 @@
+//! Shared Wgpu Context used by All Outputs
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{anyhow, Context, Result};
+use iced_wgpu::core::Pixels;
+use iced_wgpu::{Backend, Renderer};
+use raw_window_handle::{
+    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+};
+use smithay_client_toolkit::session_lock::SessionLockSurface;
+use wayland_client::{Connection, Proxy};
+
+use dynlock_lib::{ParamValue, Settings, TextureSource};
+use wgpu::util::DeviceExt;
+
+use super::mipmap::MipGenerator;
+use super::pack::{self, CompiledPack, Pass, ShaderPack, Target, MAX_CHANNELS, MAX_TEXTURES};
+use super::screenshot::{self, Background};
+use super::shader::{self, Shader};
+use super::state::UNIFORMS_SIZE;
+use super::style;
+use crate::user;
+
+const FRAG_SHADER: &'static str = include_str!("../shaders/error.glsl");
+const VERT_SHADER: &'static str = include_str!("../shaders/vertex.wgsl");
+const CROSSFADE_SHADER: &'static str = include_str!("../shaders/crossfade.wgsl");
+
+/// Width and Height of the Avatar Shader Texture
+const AVATAR_TEXTURE_SIZE: u32 = 256;
+
+/// Texture Format of Intermediate Shader Pack Buffers
+pub const BUFFER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Build Wgpu Surface from Raw Wayland Handles
+fn create_surface(
+    instance: &wgpu::Instance,
+    conn: &Connection,
+    lock_surface: &SessionLockSurface,
+) -> Result<wgpu::Surface<'static>> {
+    let raw_display_handle = RawDisplayHandle::Wayland(WaylandDisplayHandle::new(
+        NonNull::new(conn.backend().display_ptr() as *mut _).unwrap(),
+    ));
+    let raw_window_handle = RawWindowHandle::Wayland(WaylandWindowHandle::new(
+        NonNull::new(lock_surface.wl_surface().id().as_ptr() as *mut _).unwrap(),
+    ));
+    let surface = unsafe {
+        instance
+            .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                raw_display_handle,
+                raw_window_handle,
+            })
+            .context("wgpu - failed to adopt surface handle")?
+    };
+    Ok(surface)
+}
+
+/// Bind Group Layout Entry for a Filterable Shader Input Texture
+fn texture_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    }
+}
+
+/// Compile Fragment Shader and Build a Render Pipeline around it
+///
+/// Wgpu validation errors are captured rather than aborting the process.
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vertex: &Shader,
+    format: wgpu::TextureFormat,
+    source: &str,
+    language: shader::Language,
+    path: &str,
+) -> Result<wgpu::RenderPipeline> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let fragment = shader::compile(
+        device,
+        "Fragment Shader",
+        source,
+        language,
+        naga::ShaderStage::Fragment,
+        path,
+    );
+    let pipeline = fragment.map(|fragment| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &vertex.module,
+                entry_point: &vertex.entry_point,
+                buffers: &[],
+            },
+            multiview: None,
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment.module,
+                entry_point: &fragment.entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+                unclipped_depth: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        })
+    });
+    let error = pollster::block_on(device.pop_error_scope());
+    let pipeline = pipeline?;
+    match error {
+        Some(err) => Err(anyhow!("{err}")),
+        None => Ok(pipeline),
+    }
+}
+
+/// Configuration Applied to Shader Packs when Compiled
+struct Overrides {
+    params: BTreeMap<String, BTreeMap<String, ParamValue>>,
+    textures: BTreeMap<String, Vec<TextureSource>>,
+    wallpaper: Option<PathBuf>,
+    avatar: Option<PathBuf>,
+}
+
+impl Overrides {
+    fn new(settings: &Settings) -> Self {
+        Self {
+            params: settings.params.clone(),
+            textures: settings.textures.clone(),
+            wallpaper: settings.wallpaper.clone(),
+            avatar: settings.avatar.clone(),
+        }
+    }
+}
+
+/// Load and Upload Shader Texture Inputs
+///
+/// Configured textures replace those declared in the shader manifest.
+fn load_textures(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mips: &MipGenerator,
+    pack: &ShaderPack,
+    overrides: &Overrides,
+) -> Result<[Option<wgpu::TextureView>; MAX_TEXTURES]> {
+    // configured paths are already resolved against the config directory
+    let sources = match overrides.textures.get(&pack.name) {
+        Some(sources) => pack::resolve_textures(sources, Path::new("."))?,
+        None => pack.meta.textures.clone(),
+    };
+    let mut textures: [Option<wgpu::TextureView>; MAX_TEXTURES] = Default::default();
+    for (slot, source) in sources.into_iter().enumerate() {
+        let rgba = match source {
+            TextureSource::Image(path) => screenshot::load_image(&path)?,
+            TextureSource::Wallpaper => match overrides.wallpaper.as_ref() {
+                Some(path) => screenshot::load_image(path)?,
+                None => {
+                    log::warn!("wgpu - no wallpaper configured, using screenshot instead");
+                    continue;
+                }
+            },
+            TextureSource::Avatar => match overrides.avatar.as_ref() {
+                Some(path) => user::avatar(path, AVATAR_TEXTURE_SIZE)?,
+                None => {
+                    log::warn!("wgpu - no avatar found, using screenshot instead");
+                    continue;
+                }
+            },
+        };
+        log::debug!("wgpu - uploading shader texture {slot}");
+        let texture = screenshot::texture(rgba, device, queue, mips, "shader_texture");
+        textures[slot] = Some(texture.create_view(&wgpu::TextureViewDescriptor::default()));
+    }
+    Ok(textures)
+}
+
+/// Compile Every Pass of a Shader Pack into Render Pipelines
+///
+/// The image pass renders to the surface format while buffer passes
+/// render into intermediate floating point textures.
+#[allow(clippy::too_many_arguments)]
+fn create_pack(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::PipelineLayout,
+    vertex: &Shader,
+    format: wgpu::TextureFormat,
+    mips: &MipGenerator,
+    pack: &ShaderPack,
+    overrides: &Overrides,
+) -> Result<CompiledPack> {
+    let passes = pack
+        .passes
+        .iter()
+        .map(|pass| {
+            log::debug!("wgpu - compiling {:?} pass {:?}", pass.output, pass.path);
+            let format = match pass.output {
+                Target::Image => format,
+                _ => BUFFER_FORMAT,
+            };
+            let pipeline = create_pipeline(
+                device,
+                layout,
+                vertex,
+                format,
+                &pass.source,
+                shader::Language::from_path(&pass.path),
+                &pass.path.to_string_lossy(),
+            )?;
+            Ok(Pass {
+                output: pass.output,
+                scale: pass.scale,
+                inputs: pass.inputs.clone(),
+                pipeline,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // encode shader parameters with any configured overrides
+    let contents = pack.meta.uniforms(overrides.params.get(&pack.name))?;
+    let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Shader Parameters"),
+        contents: &contents,
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let textures = load_textures(device, queue, mips, pack, overrides)?;
+    Ok(CompiledPack {
+        passes,
+        params,
+        textures,
+    })
+}
+
+/// Graphics Components Shared Between Every Output Renderer
+pub struct GraphicsContext {
+    instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub format: wgpu::TextureFormat,
+    pub crossfade: wgpu::RenderPipeline,
+    pack: Mutex<Arc<CompiledPack>>,
+    overrides: Overrides,
+    shader_error: Mutex<Option<String>>,
+    pipeline_layout: wgpu::PipelineLayout,
+    vertex: Shader,
+    mips: MipGenerator,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    textures: Mutex<BTreeMap<String, wgpu::Texture>>,
+    iced: Option<Mutex<Renderer>>,
+    lost: Arc<AtomicBool>,
+}
+
+impl GraphicsContext {
+    /// Build Shared Wgpu Context Compatible with the Given Lock Surface
+    ///
+    /// Returns the adopted wgpu surface alongside the new context.
+    pub async fn new(
+        conn: &Connection,
+        settings: &Settings,
+        lock_surface: &SessionLockSurface,
+    ) -> Result<(Self, wgpu::Surface<'static>)> {
+        // spawn wgpu instance
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let surface = create_surface(&instance, conn, lock_surface)?;
+        // build device/queue from adapter
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .context("wgpu - adapter init failed")?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await
+            .context("wgpu - failed to create device/queue")?;
+        // validation and device errors must never abort the lockscreen,
+        // instead renderers fallback to software once the device is unusable
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&lost);
+        device.on_uncaptured_error(Box::new(move |err| {
+            log::error!("wgpu - {err}");
+            flag.store(true, Ordering::Relaxed);
+        }));
+        let flag = Arc::clone(&lost);
+        device.set_device_lost_callback(move |reason, message| {
+            log::error!("wgpu - device lost ({reason:?}): {message}");
+            flag.store(true, Ordering::Relaxed);
+        });
+        // compile shader components
+        log::debug!("wgpu - compiling vertex shader");
+        let vertex = shader::compile(
+            &device,
+            "Vertex Shader",
+            VERT_SHADER,
+            shader::Language::Wgsl,
+            naga::ShaderStage::Vertex,
+            "vertex.wgsl",
+        )
+        .context("failed to compile vertex shader")?;
+        let mips = MipGenerator::new(&device, &vertex)?;
+        // build bind group layout
+        log::debug!("wgpu - building bind group layout");
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    // This should match the filterable field of the
+                    // corresponding Texture entry above.
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(UNIFORMS_SIZE),
+                    },
+                    count: None,
+                },
+                texture_layout_entry(3),
+                texture_layout_entry(4),
+                texture_layout_entry(5),
+                texture_layout_entry(6),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_layout_entry(8),
+                texture_layout_entry(9),
+                texture_layout_entry(10),
+                texture_layout_entry(11),
+            ],
+            label: Some("texture_bind_group_layout"),
+        });
+        // build rendering pipeline
+        log::debug!("wgpu - building rendering pipeline");
+        let capabilities = surface.get_capabilities(&adapter);
+        let texture_format = capabilities.formats[0];
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        // compile pipeline used to blend between rotated shaders
+        let crossfade = create_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &vertex,
+            texture_format,
+            CROSSFADE_SHADER,
+            shader::Language::Wgsl,
+            "crossfade.wgsl",
+        )
+        .context("failed to compile crossfade shader")?;
+        // attempt to load and compile shader passes
+        log::debug!("wgpu - compiling fragment shaders");
+        let mut shader_error = None;
+        let overrides = Overrides::new(settings);
+        let pack = ShaderPack::load(&settings.shader_path)
+            .and_then(|pack| {
+                create_pack(
+                    &device,
+                    &queue,
+                    &render_pipeline_layout,
+                    &vertex,
+                    texture_format,
+                    &mips,
+                    &pack,
+                    &overrides,
+                )
+            })
+            .or_else(|err| {
+                log::error!("failed to compile fragment shader: {err:#}");
+                shader_error = Some(format!("{err:#}"));
+                let fallback = ShaderPack::single(Path::new("error.glsl"), FRAG_SHADER.to_owned());
+                create_pack(
+                    &device,
+                    &queue,
+                    &render_pipeline_layout,
+                    &vertex,
+                    texture_format,
+                    &mips,
+                    &fallback,
+                    &overrides,
+                )
+            })
+            .context("fallback error shader compilation failed")?;
+        // spawn iced rendering backend (also used for shader diagnostics),
+        // screensavers with a grace period need it once they escalate
+        let ui = settings.lock || settings.grace.is_some() || settings.watch;
+        let iced = match ui || shader_error.is_some() {
+            true => {
+                log::debug!("iced - building shared rendering backend");
+                style::load_font(&settings.theme);
+                let font = style::font(&settings.theme);
+                let settings = iced_wgpu::Settings::default();
+                let engine = Backend::new(&device, &queue, settings, texture_format);
+                let renderer = Renderer::new(engine, font, Pixels::from(32));
+                Some(Mutex::new(renderer))
+            }
+            false => None,
+        };
+        // return compiled context object
+        let context = Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            format: texture_format,
+            crossfade,
+            pack: Mutex::new(Arc::new(pack)),
+            overrides,
+            shader_error: Mutex::new(shader_error),
+            pipeline_layout: render_pipeline_layout,
+            vertex,
+            mips,
+            bind_group_layout,
+            sampler,
+            textures: Mutex::new(BTreeMap::new()),
+            iced,
+            lost,
+        };
+        Ok((context, surface))
+    }
+
+    /// Check if the Device has been Lost or Raised an Unrecoverable Error
+    #[inline]
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Current Compiled Shader Pack used by New Outputs
+    pub fn pack(&self) -> Arc<CompiledPack> {
+        let pack = self.pack.lock().expect("pack lock failed");
+        Arc::clone(&pack)
+    }
+
+    /// Diagnostics of the Most Recent Failed Shader Compilation
+    pub fn shader_error(&self) -> Option<String> {
+        let error = self.shader_error.lock().expect("shader error lock failed");
+        error.clone()
+    }
+
+    /// Replace Compiled Shader Pack used by New Outputs
+    pub fn set_pack(&self, pack: Arc<CompiledPack>) {
+        *self.pack.lock().expect("pack lock failed") = pack;
+    }
+
+    /// Load Shader or Shader Pack and Compile its Render Pipelines
+    pub fn load(&self, path: &Path) -> Result<Arc<CompiledPack>> {
+        let pack = ShaderPack::load(path)?;
+        let compiled = create_pack(
+            &self.device,
+            &self.queue,
+            &self.pipeline_layout,
+            &self.vertex,
+            self.format,
+            &self.mips,
+            &pack,
+            &self.overrides,
+        )?;
+        Ok(Arc::new(compiled))
+    }
+
+    /// Reload Shader or Shader Pack and Recompile its Render Pipelines
+    ///
+    /// The last good pack is kept if loading or compilation fails.
+    pub fn reload(&self, path: &Path) -> Result<Arc<CompiledPack>> {
+        let result = self.load(path);
+        let mut error = self.shader_error.lock().expect("shader error lock failed");
+        match result {
+            Ok(pack) => {
+                *error = None;
+                self.set_pack(Arc::clone(&pack));
+                Ok(pack)
+            }
+            Err(err) => {
+                *error = Some(format!("{err:#}"));
+                Err(err)
+            }
+        }
+    }
+
+    /// Adopt Additional Lock Surface using the Existing Context
+    pub fn create_surface(
+        &self,
+        conn: &Connection,
+        lock_surface: &SessionLockSurface,
+    ) -> Result<wgpu::Surface<'static>> {
+        let surface = create_surface(&self.instance, conn, lock_surface)?;
+        let capabilities = surface.get_capabilities(&self.adapter);
+        if !capabilities.formats.contains(&self.format) {
+            log::warn!(
+                "wgpu - surface does not support shared format {:?}",
+                self.format
+            );
+        }
+        Ok(surface)
+    }
+
+    /// Acquire Shared Iced Renderer
+    ///
+    /// Only available in lockscreen mode or when displaying shader errors.
+    pub fn renderer(&self) -> MutexGuard<'_, Renderer> {
+        self.iced
+            .as_ref()
+            .expect("iced renderer not initialized")
+            .lock()
+            .expect("iced renderer lock failed")
+    }
+
+    /// Retrieve View of Background Image (Uploaded Once per Key)
+    pub fn background(&self, key: &str, load: impl FnOnce() -> Background) -> wgpu::TextureView {
+        let mut textures = self.textures.lock().expect("texture cache lock failed");
+        let texture = textures.entry(key.to_owned()).or_insert_with(|| {
+            log::debug!("wgpu - uploading background texture {key:?}");
+            let (device, queue) = (&self.device, &self.queue);
+            screenshot::texture(load(), device, queue, &self.mips, "screenshot_texture")
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Drop Cached Background Textures (Views in Use Remain Valid)
+    pub fn clear_backgrounds(&self) {
+        let mut textures = self.textures.lock().expect("texture cache lock failed");
+        textures.clear();
+    }
+
+    /// Build Bind Group for Background Image, Uniforms and Pass Inputs
+    pub fn bind_group(
+        &self,
+        background: &wgpu::TextureView,
+        uniforms: &wgpu::Buffer,
+        channels: [&wgpu::TextureView; MAX_CHANNELS],
+        params: &wgpu::Buffer,
+        textures: [&wgpu::TextureView; MAX_TEXTURES],
+    ) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(background),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(channels[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(channels[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(channels[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(channels[3]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: wgpu::BindingResource::TextureView(textures[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: wgpu::BindingResource::TextureView(textures[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: wgpu::BindingResource::TextureView(textures[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 11,
+                    resource: wgpu::BindingResource::TextureView(textures[3]),
+                },
+            ],
+            label: Some("diffuse_bind_group"),
+        })
+    }
+}
@@ -1,9 +1,18 @@
 //! WGPU Rendering Implementation
 
+mod context;
+mod mipmap;
+mod pack;
 mod screenshot;
+mod shader;
+mod software;
 mod state;
 mod style;
 mod ui;
 
-pub use screenshot::Background;
+pub use context::GraphicsContext;
+pub use pack::ShaderPack;
+pub use screenshot::Backgrounds;
+pub use shader::check_shader;
+pub use software::SoftwareState;
 pub use state::State;
@@ -0,0 +1,559 @@
+//! Multi-Pass Shader Pack Definitions
+//!
+//! A shader pack is a directory containing a `pack.yaml` manifest that
+//! declares Shadertoy style render passes executed in order:
+//!
+//! ```yaml
+//! passes:
+//!   - output: buffer_a
+//!     shader: buffer_a.glsl
+//!     scale: 0.5
+//!     inputs: [buffer_a, screenshot]
+//!   - output: image
+//!     shader: image.glsl
+//!     inputs: [buffer_a]
+//! ```
+//!
+//! Inputs are bound to `iChannel0-3` in the order given. A pass reading a
+//! buffer that has not yet been rendered this frame (including its own
+//! output) receives that buffer's contents from the previous frame.
+//!
+//! Shader metadata and tunable parameters are declared alongside the passes
+//! in `pack.yaml`, or in a sidecar `<name>.yaml` next to a single shader.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use dynlock_lib::{ParamValue, TextureSource};
+
+/// Filename of the Manifest Declaring a Shader Pack
+pub const PACK_MANIFEST: &'static str = "pack.yaml";
+
+/// File Extension of Single Shader Sidecar Manifests
+pub const SIDECAR_EXTENSION: &'static str = "yaml";
+
+/// Maximum Number of Input Channels per Pass
+pub const MAX_CHANNELS: usize = 4;
+
+/// Maximum Number of Extra Texture Inputs per Shader
+pub const MAX_TEXTURES: usize = 4;
+
+#[inline]
+fn _scale() -> f32 {
+    1.0
+}
+
+/// Render Pass Output Target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    BufferA,
+    BufferB,
+    BufferC,
+    BufferD,
+    Image,
+}
+
+/// Texture Bound to a Pass Input Channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    Screenshot,
+    BufferA,
+    BufferB,
+    BufferC,
+    BufferD,
+}
+
+impl Input {
+    /// Buffer Target Read by the Input (if any)
+    pub fn target(&self) -> Option<Target> {
+        match self {
+            Self::Screenshot => None,
+            Self::BufferA => Some(Target::BufferA),
+            Self::BufferB => Some(Target::BufferB),
+            Self::BufferC => Some(Target::BufferC),
+            Self::BufferD => Some(Target::BufferD),
+        }
+    }
+}
+
+/// Shader Parameter Type
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    #[default]
+    Float,
+    Int,
+    Bool,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+impl ParamType {
+    /// Number of Components within the Parameter
+    fn components(&self) -> usize {
+        match self {
+            Self::Float | Self::Int | Self::Bool => 1,
+            Self::Vec2 => 2,
+            Self::Vec3 => 3,
+            Self::Vec4 => 4,
+        }
+    }
+
+    /// Alignment in Bytes within a std140 Uniform Block
+    fn alignment(&self) -> usize {
+        match self {
+            Self::Float | Self::Int | Self::Bool => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 | Self::Vec4 => 16,
+        }
+    }
+}
+
+/// Tunable Shader Parameter Declaration
+#[derive(Debug, Clone, Deserialize)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: ParamType,
+    pub default: ParamValue,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub description: Option<String>,
+}
+
+impl Param {
+    /// Encode Value as Uniform Bytes (Clamped to the Declared Range)
+    fn encode(&self, value: &ParamValue) -> Result<Vec<u8>> {
+        let clamp = |v: f32| {
+            let v = self.min.map_or(v, |min| v.max(min));
+            self.max.map_or(v, |max| v.min(max))
+        };
+        let components = self.kind.components();
+        match (self.kind, value) {
+            (ParamType::Bool, ParamValue::Bool(b)) => Ok((*b as u32).to_ne_bytes().to_vec()),
+            (ParamType::Int, ParamValue::Int(i)) => {
+                Ok((clamp(*i as f32).round() as i32).to_ne_bytes().to_vec())
+            }
+            (ParamType::Float, ParamValue::Int(i)) => Ok(clamp(*i as f32).to_ne_bytes().to_vec()),
+            (ParamType::Float, ParamValue::Float(f)) => Ok(clamp(*f as f32).to_ne_bytes().to_vec()),
+            (_, ParamValue::Vector(v)) if components > 1 && v.len() == components => {
+                Ok(v.iter().flat_map(|c| clamp(*c).to_ne_bytes()).collect())
+            }
+            _ => Err(anyhow!(
+                "invalid value {value:?} for {:?} parameter {:?}",
+                self.kind,
+                self.name
+            )),
+        }
+    }
+}
+
+/// Descriptive Shader Metadata and Tunable Parameters
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub license: Option<String>,
+    pub preview: Option<PathBuf>,
+    pub params: Vec<Param>,
+    pub textures: Vec<TextureSource>,
+}
+
+impl Metadata {
+    /// Resolve Paths Relative to the Manifest and Validate Parameter Defaults
+    fn resolve(mut self, dir: &Path) -> Result<Self> {
+        self.preview = self.preview.map(|preview| dir.join(preview));
+        self.uniforms(None)?;
+        self.textures = resolve_textures(&self.textures, dir)?;
+        Ok(self)
+    }
+
+    /// Encode Parameters into a std140 Uniform Block Applying Overrides
+    ///
+    /// Invalid or unknown overrides are ignored in favour of the defaults.
+    pub fn uniforms(&self, overrides: Option<&BTreeMap<String, ParamValue>>) -> Result<Vec<u8>> {
+        if let Some(overrides) = overrides {
+            for name in overrides.keys() {
+                if !self.params.iter().any(|param| &param.name == name) {
+                    log::warn!("pack - unknown shader parameter {name:?}");
+                }
+            }
+        }
+        let mut data: Vec<u8> = Vec::new();
+        for param in self.params.iter() {
+            let value = overrides
+                .and_then(|overrides| overrides.get(&param.name))
+                .and_then(|value| {
+                    param
+                        .encode(value)
+                        .map_err(|err| log::warn!("pack - ignoring override: {err}"))
+                        .ok()
+                });
+            let value = match value {
+                Some(value) => value,
+                None => param.encode(&param.default)?,
+            };
+            let align = param.kind.alignment();
+            data.resize(data.len().next_multiple_of(align), 0);
+            data.extend(value);
+        }
+        // uniform blocks are sized in multiples of 16 bytes
+        data.resize(data.len().next_multiple_of(16).max(16), 0);
+        Ok(data)
+    }
+}
+
+/// Resolve Texture Image Paths Relative to a Directory and Validate them
+pub fn resolve_textures(sources: &[TextureSource], dir: &Path) -> Result<Vec<TextureSource>> {
+    if sources.len() > MAX_TEXTURES {
+        return Err(anyhow!("shader exceeds {MAX_TEXTURES} textures"));
+    }
+    sources
+        .iter()
+        .map(|source| match source {
+            TextureSource::Image(path) => {
+                let path = shellexpand::tilde(&path.to_string_lossy()).to_string();
+                let path = dir.join(path);
+                match path.is_file() {
+                    true => Ok(TextureSource::Image(path)),
+                    false => Err(anyhow!("no such texture image: {path:?}")),
+                }
+            }
+            source => Ok(source.clone()),
+        })
+        .collect()
+}
+
+/// Read and Parse YAML Manifest
+fn read_manifest<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    log::debug!("pack - reading manifest {path:?}");
+    let data = std::fs::read_to_string(path).context(format!("failed to read {path:?}"))?;
+    serde_yaml::from_str(&data).context(format!("invalid manifest {path:?}"))
+}
+
+/// Pass Declaration within a Shader Pack Manifest
+#[derive(Debug, Clone, Deserialize)]
+struct PassConfig {
+    output: Target,
+    shader: PathBuf,
+    #[serde(default = "_scale")]
+    scale: f32,
+    #[serde(default)]
+    inputs: Vec<Input>,
+}
+
+/// Validate Pass Ordering and Configuration of a Shader Pack
+fn validate_passes(passes: &[PassConfig]) -> Result<()> {
+    let mut outputs = Vec::new();
+    for (n, pass) in passes.iter().enumerate() {
+        if outputs.contains(&pass.output) {
+            return Err(anyhow!("pass {:?} declared more than once", pass.output));
+        }
+        if pass.output == Target::Image && n + 1 != passes.len() {
+            return Err(anyhow!("image pass must be the final pass"));
+        }
+        if pass.inputs.len() > MAX_CHANNELS {
+            return Err(anyhow!(
+                "pass {:?} exceeds {MAX_CHANNELS} inputs",
+                pass.output
+            ));
+        }
+        if !pass.scale.is_finite() || pass.scale <= 0.0 {
+            return Err(anyhow!("pass {:?} has invalid scale", pass.output));
+        }
+        outputs.push(pass.output);
+    }
+    if outputs.last() != Some(&Target::Image) {
+        return Err(anyhow!("shader pack is missing an image pass"));
+    }
+    // buffers may be read before they are rendered, but must be rendered somewhere
+    for pass in passes {
+        let missing = (pass.inputs.iter())
+            .filter_map(|input| input.target())
+            .find(|target| !outputs.contains(target));
+        if let Some(target) = missing {
+            return Err(anyhow!(
+                "pass {:?} reads {target:?} which no pass renders",
+                pass.output
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Shader Pack Manifest Contents
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+    #[serde(flatten)]
+    meta: Metadata,
+    passes: Vec<PassConfig>,
+}
+
+/// Shader Source and Configuration of a Single Render Pass
+#[derive(Debug, Clone)]
+pub struct PassSource {
+    pub output: Target,
+    pub path: PathBuf,
+    pub source: String,
+    pub scale: f32,
+    pub inputs: Vec<Input>,
+}
+
+/// Loaded Shader Pack Ready for Compilation
+#[derive(Debug, Clone)]
+pub struct ShaderPack {
+    pub name: String,
+    pub meta: Metadata,
+    pub passes: Vec<PassSource>,
+}
+
+impl ShaderPack {
+    /// Check if Path is a Shader Pack Directory
+    pub fn is_pack(path: &Path) -> bool {
+        path.join(PACK_MANIFEST).is_file()
+    }
+
+    /// Name of the Shader used to Key Configuration Overrides
+    pub fn name(path: &Path) -> String {
+        path.file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Sidecar Manifest Path of a Single Fragment Shader
+    fn sidecar(path: &Path) -> PathBuf {
+        path.with_extension(SIDECAR_EXTENSION)
+    }
+
+    /// Wrap a Single Fragment Shader as an Image Pass Reading the Screenshot
+    pub fn single(path: &Path, source: String) -> Self {
+        Self {
+            name: Self::name(path),
+            meta: Metadata::default(),
+            passes: vec![PassSource {
+                output: Target::Image,
+                path: path.to_owned(),
+                source,
+                scale: 1.0,
+                inputs: vec![Input::Screenshot],
+            }],
+        }
+    }
+
+    /// Load Shader Pack Directory or Single Fragment Shader
+    pub fn load(path: &Path) -> Result<Self> {
+        if !Self::is_pack(path) {
+            let source = std::fs::read_to_string(path).context("failed to read shader file")?;
+            let mut pack = Self::single(path, source);
+            let sidecar = Self::sidecar(path);
+            if sidecar.is_file() {
+                let dir = path.parent().unwrap_or(Path::new("."));
+                pack.meta = read_manifest::<Metadata>(&sidecar)?.resolve(dir)?;
+            }
+            return Ok(pack);
+        }
+        let manifest: Manifest = read_manifest(&path.join(PACK_MANIFEST))?;
+        validate_passes(&manifest.passes)?;
+        // load shader sources relative to the pack directory
+        let passes = manifest
+            .passes
+            .into_iter()
+            .map(|pass| {
+                let shader = path.join(&pass.shader);
+                let source = std::fs::read_to_string(&shader)
+                    .context(format!("failed to read pass shader {shader:?}"))?;
+                Ok(PassSource {
+                    output: pass.output,
+                    path: shader,
+                    source,
+                    scale: pass.scale,
+                    inputs: pass.inputs,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            name: Self::name(path),
+            meta: manifest.meta.resolve(path)?,
+            passes,
+        })
+    }
+
+    /// Latest Modification Time of the Shader, its Sidecar or any File in the Pack
+    pub fn modified(path: &Path) -> std::io::Result<SystemTime> {
+        let mut modified = std::fs::metadata(path)?.modified()?;
+        if let Ok(time) = std::fs::metadata(Self::sidecar(path)).and_then(|m| m.modified()) {
+            modified = modified.max(time);
+        }
+        if Self::is_pack(path) {
+            for entry in std::fs::read_dir(path)?.filter_map(|e| e.ok()) {
+                if let Ok(time) = entry.metadata().and_then(|m| m.modified()) {
+                    modified = modified.max(time);
+                }
+            }
+        }
+        Ok(modified)
+    }
+}
+
+/// Compiled Render Pass
+pub struct Pass {
+    pub output: Target,
+    pub scale: f32,
+    pub inputs: Vec<Input>,
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+/// Compiled Shader Pack Passes, Parameter Uniforms and Textures
+///
+/// Unset texture slots are bound to the output's screenshot.
+pub struct CompiledPack {
+    pub passes: Vec<Pass>,
+    pub params: wgpu::Buffer,
+    pub textures: [Option<wgpu::TextureView>; MAX_TEXTURES],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build Parameter Declaration without a Range
+    fn param(name: &str, kind: ParamType, default: ParamValue) -> Param {
+        Param {
+            name: name.to_owned(),
+            kind,
+            default,
+            min: None,
+            max: None,
+            description: None,
+        }
+    }
+
+    /// Encode Parameters into 4 Byte Words for Comparison
+    fn words(params: Vec<Param>, overrides: Option<&BTreeMap<String, ParamValue>>) -> Vec<u32> {
+        let meta = Metadata {
+            params,
+            ..Default::default()
+        };
+        let data = meta.uniforms(overrides).expect("failed to encode uniforms");
+        assert_eq!(data.len() % 16, 0, "uniform block is not padded");
+        (data.chunks_exact(4))
+            .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Float Bits of Every Value
+    fn bits(values: &[f32]) -> Vec<u32> {
+        values.iter().map(|v| v.to_bits()).collect()
+    }
+
+    #[test]
+    fn uniforms_pad_empty_block() {
+        assert_eq!(words(vec![], None), vec![0; 4]);
+    }
+
+    #[test]
+    fn uniforms_align_vec2_to_8_bytes() {
+        let params = vec![
+            param("a", ParamType::Float, ParamValue::Float(1.0)),
+            param("b", ParamType::Vec2, ParamValue::Vector(vec![2.0, 3.0])),
+        ];
+        assert_eq!(words(params, None), bits(&[1.0, 0.0, 2.0, 3.0]));
+    }
+
+    #[test]
+    fn uniforms_align_vec3_and_vec4_to_16_bytes() {
+        let params = vec![
+            param("a", ParamType::Float, ParamValue::Float(1.0)),
+            param(
+                "b",
+                ParamType::Vec3,
+                ParamValue::Vector(vec![2.0, 3.0, 4.0]),
+            ),
+            param("c", ParamType::Float, ParamValue::Float(5.0)),
+            param(
+                "d",
+                ParamType::Vec4,
+                ParamValue::Vector(vec![6.0, 7.0, 8.0, 9.0]),
+            ),
+            param("e", ParamType::Vec2, ParamValue::Vector(vec![10.0, 11.0])),
+        ];
+        let expected = bits(&[
+            1.0, 0.0, 0.0, 0.0, // a (padded before the vec3)
+            2.0, 3.0, 4.0, 5.0, // b followed by c in its trailing slot
+            6.0, 7.0, 8.0, 9.0, // d
+            10.0, 11.0, 0.0, 0.0, // e (block padded to 16 bytes)
+        ]);
+        assert_eq!(words(params, None), expected);
+    }
+
+    #[test]
+    fn uniforms_encode_scalars_and_overrides() {
+        let mut clamped = param("c", ParamType::Float, ParamValue::Float(0.5));
+        clamped.max = Some(1.0);
+        let params = vec![
+            param("a", ParamType::Int, ParamValue::Int(-3)),
+            param("b", ParamType::Bool, ParamValue::Bool(true)),
+            clamped,
+            param("d", ParamType::Float, ParamValue::Int(2)),
+        ];
+        let overrides = BTreeMap::from([
+            ("c".to_owned(), ParamValue::Float(4.0)),
+            ("d".to_owned(), ParamValue::Bool(false)),
+        ]);
+        let expected = vec![(-3i32) as u32, 1, 1.0f32.to_bits(), 2.0f32.to_bits()];
+        assert_eq!(words(params, Some(&overrides)), expected);
+    }
+
+    /// Parse Pass Declarations from a Manifest Snippet
+    fn passes(yaml: &str) -> Vec<PassConfig> {
+        serde_yaml::from_str(yaml).expect("invalid test manifest")
+    }
+
+    #[test]
+    fn passes_accept_previous_frame_buffers() {
+        let passes = passes(
+            "
+            - { output: buffer_a, shader: a.glsl, inputs: [buffer_a, buffer_b] }
+            - { output: buffer_b, shader: b.glsl, inputs: [buffer_a] }
+            - { output: image, shader: image.glsl, inputs: [buffer_b, screenshot] }
+            ",
+        );
+        assert!(validate_passes(&passes).is_ok());
+    }
+
+    #[test]
+    fn passes_reject_unrendered_buffers() {
+        let passes = passes(
+            "
+            - { output: buffer_a, shader: a.glsl }
+            - { output: image, shader: image.glsl, inputs: [buffer_b] }
+            ",
+        );
+        let err = validate_passes(&passes).unwrap_err();
+        assert!(err.to_string().contains("BufferB"), "{err}");
+    }
+
+    #[test]
+    fn passes_reject_unknown_inputs() {
+        let yaml = "[{ output: image, shader: image.glsl, inputs: [bufer_a] }]";
+        assert!(serde_yaml::from_str::<Vec<PassConfig>>(yaml).is_err());
+    }
+
+    #[test]
+    fn passes_reject_invalid_order() {
+        let passes = passes(
+            "
+            - { output: image, shader: image.glsl }
+            - { output: buffer_a, shader: a.glsl }
+            ",
+        );
+        assert!(validate_passes(&passes).is_err());
+    }
+}
@@ -1,44 +1,195 @@
 //! Wgpu Rendering BindGroup
 
-use wgpu::util::DeviceExt;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use libwayshot::WayshotConnection;
+use wayland_client::protocol::wl_output::Transform;
+use wayland_client::Connection;
+
+use super::mipmap::{MipGenerator, TEXTURE_FORMAT};
 
 pub type Background = image::ImageBuffer<image::Rgba<u8>, Vec<u8>>;
 
-/// Take Screenshot and make Wgpu Texture
-pub fn screenshot(rgba: Background, device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+/// Per-Output Background Images Keyed by Output Name
+pub struct Backgrounds {
+    fallback: Option<Background>,
+    outputs: BTreeMap<String, Background>,
+}
+
+impl Backgrounds {
+    /// Use a Single Background Image for Every Output
+    pub fn image(rgba: Background) -> Self {
+        Self {
+            fallback: Some(rgba),
+            outputs: BTreeMap::new(),
+        }
+    }
+
+    /// Use a Single Solid Color for Every Output
+    pub fn solid(color: image::Rgba<u8>) -> Self {
+        Self::image(Background::from_pixel(1, 1, color))
+    }
+
+    /// Load a Single Background Image File for Every Output
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::image(load_image(path)?))
+    }
+
+    /// Capture an Individual Screenshot of Every Output
+    pub fn screenshot(conn: &Connection) -> Result<Self> {
+        let wayshot = WayshotConnection::from_connection(conn.clone())
+            .context("wayshot - screenshot connection failed")?;
+        let mut outputs = BTreeMap::new();
+        for info in wayshot.get_all_outputs() {
+            let region = info.logical_region.inner;
+            log::debug!(
+                "wayshot - capturing output {:?} (position={},{} size={}x{} transform={:?})",
+                info.name,
+                region.position.x,
+                region.position.y,
+                region.size.width,
+                region.size.height,
+                info.transform,
+            );
+            // outputs that fail to capture fallback to a solid background
+            let rgba = match wayshot.screenshot_single_output(info, false) {
+                Ok(rgba) => rgba,
+                Err(err) => {
+                    log::error!(
+                        "wayshot - screenshot failed for output {:?}: {err}",
+                        info.name
+                    );
+                    continue;
+                }
+            };
+            let size = (region.size.width, region.size.height);
+            outputs.insert(info.name.to_owned(), transform(rgba, info.transform, size));
+        }
+        Ok(Self {
+            fallback: None,
+            outputs,
+        })
+    }
+
+    /// Resolve Unique Key of the Background used by the Specified Output
+    ///
+    /// Outputs sharing the same background share the same key.
+    pub fn key(&self, name: Option<&str>) -> String {
+        match name.filter(|name| self.outputs.contains_key(*name)) {
+            Some(name) => format!("output:{name}"),
+            None => "default".to_owned(),
+        }
+    }
+
+    /// Retrieve Background for the Specified Output
+    ///
+    /// Outputs without a capture fallback to the configured
+    /// image or a single black pixel.
+    pub fn get(&self, name: Option<&str>) -> Background {
+        name.and_then(|name| self.outputs.get(name))
+            .or(self.fallback.as_ref())
+            .cloned()
+            .unwrap_or_else(|| {
+                log::warn!("no background available for output {name:?}");
+                Background::from_pixel(1, 1, image::Rgba([0, 0, 0, 255]))
+            })
+    }
+}
+
+/// Convert Raw Output Capture into its Logical Orientation and Size
+fn transform(rgba: Background, transform: Transform, size: (u32, u32)) -> Background {
+    let rgba = match transform {
+        Transform::_90 => imageops::rotate90(&rgba),
+        Transform::_180 => imageops::rotate180(&rgba),
+        Transform::_270 => imageops::rotate270(&rgba),
+        Transform::Flipped => imageops::flip_horizontal(&rgba),
+        Transform::Flipped90 => imageops::rotate90(&imageops::flip_horizontal(&rgba)),
+        Transform::Flipped180 => imageops::rotate180(&imageops::flip_horizontal(&rgba)),
+        Transform::Flipped270 => imageops::rotate270(&imageops::flip_horizontal(&rgba)),
+        _ => rgba,
+    };
+    // lock surfaces are rendered at their logical size
+    let (width, height) = size;
+    if width == 0 || height == 0 || rgba.dimensions() == size {
+        return rgba;
+    }
+    imageops::resize(&rgba, width, height, FilterType::Triangle)
+}
+
+/// Load Image File as a Background Buffer
+pub fn load_image(path: &Path) -> Result<Background> {
+    let img = std::fs::read(path).context(format!("failed to read image {path:?}"))?;
+    let rgba = image::load_from_memory(&img)
+        .context(format!("invalid image {path:?}"))?
+        .to_rgba8();
+    Ok(rgba)
+}
+
+/// Number of Mipmap Levels for a Full Mip Chain of the Given Size
+fn mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Upload Image as a Wgpu Texture and Generate its Mipmaps on the GPU
+pub fn texture(
+    rgba: Background,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mips: &MipGenerator,
+    label: &str,
+) -> wgpu::Texture {
+    let (width, height) = rgba.dimensions();
+    let mip_level_count = mip_levels(width, height);
     // build wgpu texture from image
-    let dimensions = rgba.dimensions();
     let texture_size = wgpu::Extent3d {
-        width: dimensions.0,
-        height: dimensions.1,
+        width,
+        height,
         depth_or_array_layers: 1,
     };
-    let texture = device.create_texture_with_data(
-        queue,
-        &wgpu::TextureDescriptor {
-            // All textures are stored as 3D, we represent our 2D texture
-            // by setting depth to 1.
-            size: texture_size,
-            mip_level_count: 1, // We'll talk about this a little later
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            // Most images are stored using sRGB, so we need to reflect that here.
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            // TEXTURE_BINDING tells wgpu that we want to use this texture in shaders
-            // COPY_DST means that we want to copy data to this texture
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label: Some("screenshot_texture"),
-            // This is the same as with the SurfaceConfig. It
-            // specifies what texture formats can be used to
-            // create TextureViews for this texture. The base
-            // texture format (Rgba8UnormSrgb in this case) is
-            // always supported. Note that using a different
-            // texture format is not supported on the WebGL2
-            // backend.
-            view_formats: &[],
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        // All textures are stored as 3D, we represent our 2D texture
+        // by setting depth to 1.
+        size: texture_size,
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        // Most images are stored using sRGB, so we need to reflect that here.
+        format: TEXTURE_FORMAT,
+        // TEXTURE_BINDING tells wgpu that we want to use this texture in shaders
+        // COPY_DST means that we want to copy data to this texture
+        // RENDER_ATTACHMENT lets mip levels be rendered from the previous level
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        label: Some(label),
+        // This is the same as with the SurfaceConfig. It
+        // specifies what texture formats can be used to
+        // create TextureViews for this texture. The base
+        // texture format (Rgba8UnormSrgb in this case) is
+        // always supported. Note that using a different
+        // texture format is not supported on the WebGL2
+        // backend.
+        view_formats: &[],
+    });
+    // only the full resolution image is uploaded from the cpu
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
         },
-        wgpu::util::TextureDataOrder::MipMajor,
         &rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: Some(height),
+        },
+        texture_size,
     );
+    mips.generate(device, queue, &texture);
     texture
 }
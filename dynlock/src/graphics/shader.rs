@@ -0,0 +1,183 @@
+//! Fragment Shader Source Preprocessing and Compilation
+//!
+//! Fragment shaders may be written in either GLSL or WGSL and share the same
+//! binding layout within bind group 0:
+//!
+//! | Binding | GLSL                               | WGSL                                  |
+//! |---------|------------------------------------|---------------------------------------|
+//! | 0       | `uniform texture2D t_screenshot`   | `var t_screenshot: texture_2d<f32>`   |
+//! | 1       | `uniform sampler s_screenshot`     | `var s_screenshot: sampler`           |
+//! | 2       | `uniform FrameUniforms { ... }`    | `var<uniform> u: FrameUniforms`       |
+//! | 3-6     | `uniform texture2D t_channel0-3`   | `var t_channel0-3: texture_2d<f32>`   |
+//! | 7       | `uniform Params { ... }`           | `var<uniform> params: Params`         |
+//! | 8-11    | `uniform texture2D t_texture0-3`   | `var t_texture0-3: texture_2d<f32>`   |
+//!
+//! The frame uniforms follow std140 layout and are documented in the README.
+//! Channel textures are the inputs of a shader pack pass while the extra
+//! textures are declared in the shader manifest. Both default to the
+//! screenshot when unused.
+use std::borrow::Cow;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use super::pack::ShaderPack;
+
+const SHADERTOY_PRELUDE: &'static str = include_str!("../shaders/shadertoy.glsl");
+
+/// Shader Source Language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Glsl,
+    Wgsl,
+}
+
+impl Language {
+    /// Determine Language from Shader File Extension (Defaults to GLSL)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("wgsl") => Self::Wgsl,
+            _ => Self::Glsl,
+        }
+    }
+}
+
+/// Compiled Shader Module and its Entrypoint
+pub struct Shader {
+    pub module: wgpu::ShaderModule,
+    pub entry_point: String,
+}
+
+/// Check if Shader Source is a Shadertoy Style `mainImage` Shader
+fn is_shadertoy(source: &str) -> bool {
+    source.contains("mainImage") && !source.contains("void main(")
+}
+
+/// Prepare Fragment Shader Source for Compilation
+///
+/// Shadertoy shaders defining only `mainImage` have the compatibility
+/// prelude prepended and any `#version` directive removed.
+pub fn prepare(source: &str) -> Cow<'_, str> {
+    if !is_shadertoy(source) {
+        return Cow::Borrowed(source);
+    }
+    log::debug!("shader - applying shadertoy compatibility prelude");
+    let body: Vec<&str> = source
+        .lines()
+        .map(|line| match line.trim_start().starts_with("#version") {
+            true => "",
+            false => line,
+        })
+        .collect();
+    Cow::Owned(format!("{SHADERTOY_PRELUDE}#line 1\n{}\n", body.join("\n")))
+}
+
+/// Parse GLSL Source into Naga IR using Shaderc
+#[cfg(feature = "shaderc")]
+fn parse_glsl(source: &str, stage: naga::ShaderStage, path: &str) -> Result<naga::Module> {
+    let kind = match stage {
+        naga::ShaderStage::Vertex => shaderc::ShaderKind::Vertex,
+        naga::ShaderStage::Fragment => shaderc::ShaderKind::Fragment,
+        naga::ShaderStage::Compute => shaderc::ShaderKind::Compute,
+    };
+    let compiler = shaderc::Compiler::new().ok_or_else(|| anyhow!("compiler init failed"))?;
+    let spirv = compiler
+        .compile_into_spirv(&prepare(source), kind, path, "main", None)
+        .map_err(|err| anyhow!("{err}"))?;
+    let options = naga::front::spv::Options {
+        adjust_coordinate_space: false,
+        ..Default::default()
+    };
+    naga::front::spv::parse_u8_slice(spirv.as_binary_u8(), &options).map_err(|err| anyhow!("{err}"))
+}
+
+/// Parse GLSL Source into Naga IR using Naga's GLSL Frontend
+#[cfg(not(feature = "shaderc"))]
+fn parse_glsl(source: &str, stage: naga::ShaderStage, path: &str) -> Result<naga::Module> {
+    let prepared = prepare(source);
+    // report line numbers relative to the user source rather than the prelude
+    let offset = match prepared {
+        Cow::Borrowed(_) => 0,
+        Cow::Owned(_) => SHADERTOY_PRELUDE.lines().count() as u32 + 1,
+    };
+    let mut frontend = naga::front::glsl::Frontend::default();
+    let options = naga::front::glsl::Options::from(stage);
+    frontend.parse(&options, &prepared).map_err(|errors| {
+        let messages: Vec<String> = errors
+            .iter()
+            .map(|err| {
+                let location = err.meta.location(&prepared);
+                let line = location.line_number.saturating_sub(offset);
+                format!("{path}:{line}:{}: {}", location.line_position, err.kind)
+            })
+            .collect();
+        anyhow!(messages.join("\n"))
+    })
+}
+
+/// Parse and Validate Shader Source into Naga IR
+///
+/// Errors contain the compiler diagnostics prefixed with the given path.
+pub fn parse(
+    source: &str,
+    language: Language,
+    stage: naga::ShaderStage,
+    path: &str,
+) -> Result<naga::Module> {
+    let module = match language {
+        Language::Glsl => parse_glsl(source, stage, path)?,
+        Language::Wgsl => naga::front::wgsl::parse_str(source)
+            .map_err(|err| anyhow!(err.emit_to_string_with_path(source, path)))?,
+    };
+    // validation spans refer to the source naga actually parsed
+    let parsed = match language {
+        Language::Glsl => prepare(source),
+        Language::Wgsl => Cow::Borrowed(source),
+    };
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|err| anyhow!(err.emit_to_string_with_path(&parsed, path)))?;
+    Ok(module)
+}
+
+/// Validate Fragment Shader or every Shader Pack Pass without Creating a Device
+///
+/// Returns the loaded pack so its metadata can be reported.
+pub fn check_shader(path: &Path) -> Result<ShaderPack> {
+    let pack = ShaderPack::load(path)?;
+    for pass in pack.passes.iter() {
+        let language = Language::from_path(&pass.path);
+        let name = pass.path.to_string_lossy();
+        parse(&pass.source, language, naga::ShaderStage::Fragment, &name)?;
+    }
+    Ok(pack)
+}
+
+/// Compile Shader Source into Wgpu Shader Module
+pub fn compile(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+    language: Language,
+    stage: naga::ShaderStage,
+    path: &str,
+) -> Result<Shader> {
+    let module = parse(source, language, stage, path)?;
+    let entry_point = module
+        .entry_points
+        .iter()
+        .find(|entry| entry.stage == stage)
+        .map(|entry| entry.name.to_owned())
+        .ok_or_else(|| anyhow!("no {stage:?} entrypoint found"))?;
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+    });
+    Ok(Shader {
+        module,
+        entry_point,
+    })
+}
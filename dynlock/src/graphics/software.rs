@@ -0,0 +1,360 @@
+//! CPU Rendered Lock Surface used when Wgpu is Unavailable or Disabled
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use iced_wgpu::core::Font;
+use iced_wgpu::graphics::text::{self, cosmic_text};
+use image::imageops::{self, FilterType};
+use smithay_client_toolkit::shm::slot::{Buffer, SlotPool};
+use smithay_client_toolkit::shm::Shm;
+use wayland_client::protocol::{wl_shm, wl_surface};
+
+use dynlock_lib::{RendererConfig, Rgba, Settings, ThemeConfig};
+
+use super::screenshot::Background;
+use super::style;
+use super::ui::CAPS_LOCK_ICON;
+use crate::auth::AuthController;
+
+/// Authentication State Visible in the Password Box
+#[derive(Debug, Clone, PartialEq)]
+struct Indicator {
+    length: usize,
+    wrong: bool,
+    verifying: bool,
+    caps_lock: bool,
+    prompt: Option<String>,
+    messages: Vec<String>,
+}
+
+/// Mutable View of an Opaque ARGB8888 Pixel Buffer
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    width: i32,
+    height: i32,
+}
+
+impl Canvas<'_> {
+    /// Blend Color over a Single Pixel with the Given Coverage
+    fn blend(&mut self, x: i32, y: i32, color: Rgba, coverage: f32) {
+        let [r, g, b, a] = color.0;
+        let alpha = a * coverage;
+        let offset = ((y * self.width + x) * 4) as usize;
+        let pixel = &mut self.data[offset..offset + 4];
+        // wl_shm formats are little-endian so channels are stored as BGRA
+        for (channel, value) in pixel.iter_mut().zip([b, g, r]) {
+            let mixed = *channel as f32 / 255.0 * (1.0 - alpha) + value * alpha;
+            *channel = (mixed * 255.0).round() as u8;
+        }
+        pixel[3] = 255;
+    }
+
+    /// Blend Color over a Rectangle (Clipped to the Canvas)
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Rgba) {
+        let (x0, y0) = ((x.round() as i32).max(0), (y.round() as i32).max(0));
+        let x1 = ((x + width).round() as i32).min(self.width);
+        let y1 = ((y + height).round() as i32).min(self.height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.blend(px, py, color, 1.0);
+            }
+        }
+    }
+
+    /// Blend Image over the Canvas at the Given Position
+    fn image(&mut self, x: f32, y: f32, image: &Background) {
+        let (x, y) = (x.round() as i32, y.round() as i32);
+        for (ix, iy, pixel) in image.enumerate_pixels() {
+            let (px, py) = (x + ix as i32, y + iy as i32);
+            if px < 0 || py < 0 || px >= self.width || py >= self.height {
+                continue;
+            }
+            let [r, g, b, a] = pixel.0;
+            let color = Rgba::rgba8(r, g, b, a);
+            self.blend(px, py, color, 1.0);
+        }
+    }
+
+    /// Blend Horizontally Centered Line of Text Starting at the Given Height
+    ///
+    /// Returns the height of the drawn line.
+    fn text(&mut self, cache: &mut cosmic_text::SwashCache, label: &Label, y: f32) -> f32 {
+        let mut fonts = text::font_system()
+            .write()
+            .expect("font system lock failed");
+        let fonts = fonts.raw();
+        let metrics = cosmic_text::Metrics::new(label.size, label.size * 1.3);
+        let mut buffer = cosmic_text::Buffer::new(fonts, metrics);
+        buffer.set_size(fonts, self.width as f32, metrics.line_height);
+        let attrs = text::to_attributes(label.font);
+        buffer.set_text(fonts, label.content, attrs, cosmic_text::Shaping::Advanced);
+        buffer.shape_until_scroll(fonts);
+        let size = text::measure(&buffer);
+        let x = ((self.width as f32 - size.width) / 2.0).round() as i32;
+        let y = y.round() as i32;
+        // glyphs are rasterised as coverage masks tinted with the label color
+        let white = cosmic_text::Color::rgb(255, 255, 255);
+        buffer.draw(fonts, cache, white, |gx, gy, width, height, mask| {
+            let coverage = mask.a() as f32 / 255.0;
+            for py in (y + gy).max(0)..(y + gy + height as i32).min(self.height) {
+                for px in (x + gx).max(0)..(x + gx + width as i32).min(self.width) {
+                    self.blend(px, py, label.color, coverage);
+                }
+            }
+        });
+        metrics.line_height
+    }
+
+    /// Blend Color over an Anti-Aliased Circle (Clipped to the Canvas)
+    fn circle(&mut self, cx: f32, cy: f32, radius: f32, color: Rgba) {
+        let x0 = ((cx - radius).floor() as i32).max(0);
+        let y0 = ((cy - radius).floor() as i32).max(0);
+        let x1 = ((cx + radius).ceil() as i32).min(self.width);
+        let y1 = ((cy + radius).ceil() as i32).min(self.height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let (dx, dy) = (px as f32 + 0.5 - cx, py as f32 + 0.5 - cy);
+                let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+                self.blend(px, py, color, coverage);
+            }
+        }
+    }
+}
+
+/// Single Line of Text Drawn onto the Canvas
+struct Label<'a> {
+    content: &'a str,
+    font: Font,
+    size: f32,
+    color: Rgba,
+}
+
+/// Scale, Blur and Dim the Background into an Opaque Buffer of the Given Size
+fn backdrop(image: &Background, width: u32, height: u32, config: &RendererConfig) -> Vec<u8> {
+    // the blur radius spans roughly two standard deviations
+    let sigma = config.blur.max(0.0) / 2.0;
+    // blurring a downscaled copy is far cheaper than blurring at full size
+    let factor = (sigma / 2.0).max(1.0);
+    let w = ((width as f32 / factor) as u32).max(1);
+    let h = ((height as f32 / factor) as u32).max(1);
+    let mut small = imageops::resize(image, w, h, FilterType::Triangle);
+    if sigma > 0.0 {
+        small = imageops::blur(&small, sigma / factor);
+    }
+    let scaled = imageops::resize(&small, width, height, FilterType::Triangle);
+    let brightness = 1.0 - config.dim.clamp(0.0, 1.0);
+    let dim = |channel: u8| (channel as f32 * brightness).round() as u8;
+    let mut data = Vec::with_capacity(scaled.len());
+    for pixel in scaled.pixels() {
+        let [r, g, b, _] = pixel.0;
+        data.extend_from_slice(&[dim(b), dim(g), dim(r), 255]);
+    }
+    data
+}
+
+/// Draw Centered Password Box with a Dot per Typed Character
+///
+/// The caps-lock icon is drawn beside the box while caps-lock is active,
+/// the pending prompt inside the empty box and status lines below it.
+fn password_box(
+    canvas: &mut Canvas,
+    cache: &mut cosmic_text::SwashCache,
+    theme: &ThemeConfig,
+    font: Font,
+    indicator: &Indicator,
+    caps: Option<&Background>,
+) {
+    let colors = &theme.colors;
+    let (fill, border, mut dots) = match indicator.wrong {
+        true => (colors.wrong_background, colors.wrong, colors.wrong),
+        false => (colors.background, colors.border, colors.text),
+    };
+    if indicator.verifying {
+        dots.0[3] *= 0.5;
+    }
+    let radius = (theme.font_size / 3.0).max(2.0);
+    let spacing = radius * 3.0;
+    let width = theme.input_width.max(spacing * 2.0);
+    let height = radius * 2.0 + theme.padding.max(0.0) * 2.0;
+    let x = (canvas.width as f32 - width) / 2.0;
+    let y = (canvas.height as f32 - height) / 2.0;
+    let edge = theme.border_width.max(0.0);
+    canvas.rect(x - edge, y - edge, width + edge * 2.0, edge, border);
+    canvas.rect(x - edge, y + height, width + edge * 2.0, edge, border);
+    canvas.rect(x - edge, y, edge, height, border);
+    canvas.rect(x + width, y, edge, height, border);
+    canvas.rect(x, y, width, height, fill);
+    // only as many dots as fit inside the box are drawn
+    let capacity = ((width - theme.padding.max(0.0) * 2.0) / spacing)
+        .floor()
+        .max(1.0) as usize;
+    let count = indicator.length.min(capacity);
+    let start = canvas.width as f32 / 2.0 - (count.saturating_sub(1)) as f32 * spacing / 2.0;
+    for n in 0..count {
+        let cx = start + n as f32 * spacing;
+        canvas.circle(cx, canvas.height as f32 / 2.0, radius, dots);
+    }
+    if let Some(icon) = caps.filter(|_| indicator.caps_lock) {
+        let icon_y = (canvas.height as f32 - icon.height() as f32) / 2.0;
+        canvas.image(x + width + edge + theme.padding, icon_y, icon);
+    }
+    let label = |content| Label {
+        content,
+        font,
+        size: theme.font_size,
+        color: colors.text,
+    };
+    if let Some(prompt) = indicator.prompt.as_deref().filter(|_| count == 0) {
+        let line_y = (canvas.height as f32 - theme.font_size * 1.3) / 2.0;
+        canvas.text(cache, &label(prompt), line_y);
+    }
+    let mut line_y = y + height + edge + theme.padding.max(0.0);
+    for message in indicator.messages.iter() {
+        line_y += canvas.text(cache, &label(message), line_y);
+    }
+}
+
+/// Per-Output State of a Lock Surface Drawn into Shared Memory Buffers
+pub struct SoftwareState {
+    surface: wl_surface::WlSurface,
+    pool: SlotPool,
+    buffer: Option<Buffer>,
+    auth: Arc<Mutex<AuthController>>,
+    theme: ThemeConfig,
+    config: RendererConfig,
+    lock: bool,
+    background: Background,
+    base: Vec<u8>,
+    caps_img: Option<Background>,
+    caps_lock: bool,
+    font: Font,
+    glyphs: cosmic_text::SwashCache,
+    width: u32,
+    height: u32,
+    drawn: Option<Indicator>,
+}
+
+impl SoftwareState {
+    pub fn new(
+        shm: &Shm,
+        surface: wl_surface::WlSurface,
+        auth: Arc<Mutex<AuthController>>,
+        background: Background,
+        settings: &Settings,
+    ) -> Result<Self> {
+        let pool = SlotPool::new(1, shm).context("shm - failed to create buffer pool")?;
+        let size = settings.theme.icon_size.max(1.0) as u32;
+        let caps_img = image::load_from_memory(CAPS_LOCK_ICON)
+            .map(|icon| imageops::resize(&icon.to_rgba8(), size, size, FilterType::Triangle))
+            .map_err(|err| log::error!("shm - failed to load caps-lock icon: {err}"))
+            .ok();
+        style::load_font(&settings.theme);
+        Ok(Self {
+            surface,
+            pool,
+            buffer: None,
+            auth,
+            theme: settings.theme.clone(),
+            config: settings.renderer.clone(),
+            lock: settings.lock,
+            background,
+            base: vec![],
+            caps_img,
+            caps_lock: false,
+            font: style::font(&settings.theme),
+            glyphs: cosmic_text::SwashCache::new(),
+            width: 0,
+            height: 0,
+            drawn: None,
+        })
+    }
+
+    /// Configure Buffer Size to Match the Surface
+    pub fn configure(&mut self, width: u32, height: u32) {
+        log::debug!("shm - configuring surface ({width}/{height})");
+        self.width = width;
+        self.height = height;
+        self.base = backdrop(&self.background, width, height, &self.config);
+        self.drawn = None;
+    }
+
+    /// Show the Password Box once the Screensaver Escalates into a Lock
+    #[inline]
+    pub fn lock(&mut self) {
+        self.lock = true;
+        self.drawn = None;
+    }
+
+    /// Show or Hide the Caps-Lock Indicator
+    #[inline]
+    pub fn set_caps_lock(&mut self, caps_lock: bool) {
+        self.caps_lock = caps_lock;
+    }
+
+    /// Draw a New Frame when the Visible Authentication State Changed
+    ///
+    /// Returns false if no frame could be presented.
+    pub fn render(&mut self) -> bool {
+        if self.width == 0 || self.height == 0 {
+            return true;
+        }
+        let indicator = {
+            let auth = self.auth.lock().expect("auth controller lock failed");
+            Indicator {
+                length: auth.password().chars().count(),
+                wrong: auth.wrong(),
+                verifying: auth.is_verifying(),
+                caps_lock: self.caps_lock,
+                prompt: auth.prompt().map(str::to_owned),
+                messages: auth.messages(),
+            }
+        };
+        if self.drawn.as_ref() == Some(&indicator) {
+            return true;
+        }
+        let (width, height) = (self.width as i32, self.height as i32);
+        // reuse the last buffer unless resized or still held by the compositor
+        let reusable = self.buffer.as_ref().filter(|buffer| {
+            buffer.height() == height
+                && buffer.stride() == width * 4
+                && buffer.canvas(&mut self.pool).is_some()
+        });
+        let buffer = match reusable {
+            Some(_) => self.buffer.take().expect("missing shm buffer"),
+            None => {
+                let format = wl_shm::Format::Argb8888;
+                match self.pool.create_buffer(width, height, width * 4, format) {
+                    Ok((buffer, _)) => buffer,
+                    Err(err) => {
+                        log::error!("shm - failed to create buffer: {err}");
+                        return false;
+                    }
+                }
+            }
+        };
+        let Some(data) = buffer.canvas(&mut self.pool) else {
+            log::error!("shm - buffer is still in use by the compositor");
+            return false;
+        };
+        let mut canvas = Canvas {
+            data,
+            width,
+            height,
+        };
+        canvas.data.copy_from_slice(&self.base);
+        if self.lock {
+            let caps = self.caps_img.as_ref();
+            let (theme, font) = (&self.theme, self.font);
+            password_box(&mut canvas, &mut self.glyphs, theme, font, &indicator, caps);
+        }
+        if let Err(err) = buffer.attach_to(&self.surface) {
+            log::error!("shm - failed to attach buffer: {err:?}");
+            return false;
+        }
+        self.surface.damage_buffer(0, 0, width, height);
+        self.surface.commit();
+        self.buffer = Some(buffer);
+        self.drawn = Some(indicator);
+        true
+    }
+}
@@ -1,301 +1,521 @@
 //! Complete Wgpu State Definition
 
-use std::{ptr::NonNull, time::SystemTime};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::{Context, Result};
-use raw_window_handle::{
-    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
-};
-use smithay_client_toolkit::session_lock::SessionLockSurface;
-use wayland_client::{Connection, Proxy};
+use chrono::{Datelike, Timelike};
+use iced_wgpu::core::{mouse, Point};
 
-use super::{screenshot::Background, ui::IcedState};
+use dynlock_lib::{AnimationConfig, Settings};
 
-const FRAG_SHADER: &'static str = include_str!("../shaders/error.glsl");
-const VERT_SHADER: &'static str = include_str!("../shaders/shader.vert");
-pub const PUSH_CONSTANTS_SIZE: u32 = std::mem::size_of::<FrameUniforms>() as u32;
+use super::context::{GraphicsContext, BUFFER_FORMAT};
+use super::pack::{CompiledPack, Target, MAX_CHANNELS, MAX_TEXTURES};
+use super::ui::{IcedState, LockStatus};
+use crate::auth::AuthController;
 
-/// Shader Push Constant Frame Uniforms
+pub const UNIFORMS_SIZE: u64 = std::mem::size_of::<FrameUniforms>() as u64;
+
+/// Consecutive Failed Frames before the Renderer is Considered Broken
+const MAX_FAILED_FRAMES: u32 = 10;
+
+/// Shader Uniform Buffer Frame Uniforms (std140 layout)
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 struct FrameUniforms {
+    resolution: [f32; 3],
     elapsed: f32,
+    time_delta: f32,
+    frame: i32,
+    frame_rate: f32,
     fade_amount: f32,
-    resolution: [f32; 2],
+    mouse: [f32; 4],
+    date: [f32; 4],
+    failures: f32,
+    verifying: f32,
+    password_length: f32,
+    keystroke_time: f32,
+    caps_lock: f32,
+    failure_pulse: f32,
+    unlock_progress: f32,
+    buffer_pass: f32,
 }
 
 impl FrameUniforms {
-    fn new(ctx: &RenderContext) -> Self {
+    fn new(ctx: &RenderContext, iced: Option<&IcedState>) -> Self {
         let duration = SystemTime::now().duration_since(ctx.start).unwrap();
+        // screensaver mode has no ui, so treat launch as the last keystroke
+        let status = iced.map(|iced| iced.status()).unwrap_or(LockStatus {
+            keystroke_time: duration.as_secs_f32(),
+            ..Default::default()
+        });
+        let now = chrono::Local::now();
+        let seconds = now.num_seconds_from_midnight() as f32
+            + now.timestamp_subsec_nanos() as f32 / 1_000_000_000.0;
         Self {
+            resolution: [ctx.width as f32, ctx.height as f32, 1.0],
             elapsed: duration.as_secs_f32(),
-            fade_amount: 0.0,
-            resolution: [ctx.width as f32, ctx.height as f32],
+            time_delta: ctx.time_delta,
+            frame: ctx.frame as i32,
+            frame_rate: match ctx.time_delta > 0.0 {
+                true => 1.0 / ctx.time_delta,
+                false => 0.0,
+            },
+            fade_amount: ctx.fade_amount(),
+            mouse: ctx.mouse,
+            date: [
+                now.year() as f32,
+                now.month0() as f32,
+                now.day() as f32,
+                seconds,
+            ],
+            failures: status.failures,
+            verifying: status.authenticating,
+            password_length: status.password_length,
+            keystroke_time: status.keystroke_time,
+            caps_lock: status.caps_lock,
+            failure_pulse: status.failure_pulse,
+            unlock_progress: ctx.unlock_progress(),
+            buffer_pass: 0.0,
         }
     }
 }
 
+/// Fraction of Duration Elapsed since the Specified Instant (Clamped to 0-1)
+fn progress(start: Instant, duration: Duration) -> f32 {
+    match duration.is_zero() {
+        true => 1.0,
+        false => (start.elapsed().as_secs_f32() / duration.as_secs_f32()).min(1.0),
+    }
+}
+
+/// Build Iced UI State for an Output
+fn iced_state(
+    graphics: &GraphicsContext,
+    auth: Arc<Mutex<AuthController>>,
+    settings: &Settings,
+) -> IcedState {
+    IcedState::new(
+        graphics.format,
+        auth,
+        settings.theme.clone(),
+        settings.avatar.as_deref(),
+        settings.lock,
+        graphics.shader_error(),
+    )
+}
+
 /// Rendering Context used to Generate FrameUniforms
 pub struct RenderContext {
     width: usize,
     height: usize,
     start: SystemTime,
+    last_render: Option<Instant>,
+    time_delta: f32,
+    frame: u32,
+    cursor: Point,
+    mouse: [f32; 4],
+    fade_in: Duration,
+    fade_out: Duration,
+    fade_start: Instant,
+    unlock_start: Option<Instant>,
 }
 
 impl RenderContext {
-    fn new() -> Self {
+    fn new(animation: &AnimationConfig) -> Self {
         Self {
             width: 256,
             height: 256,
             start: SystemTime::now(),
+            fade_in: animation.fade_in(),
+            fade_out: animation.fade_out(),
+            fade_start: Instant::now(),
+            unlock_start: None,
+            last_render: None,
+            time_delta: 0.0,
+            frame: 0,
+            cursor: Point::ORIGIN,
+            mouse: [0.0; 4],
+        }
+    }
+
+    /// Advance Frame Counters before Rendering a New Frame
+    fn advance(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_render {
+            self.time_delta = now.duration_since(last).as_secs_f32();
+            self.frame = self.frame.wrapping_add(1);
+        }
+        self.last_render = Some(now);
+    }
+
+    /// Progress of the Unlock Fade-Out (0 until Unlocking Begins)
+    fn unlock_progress(&self) -> f32 {
+        match self.unlock_start {
+            Some(start) => progress(start, self.fade_out),
+            None => 0.0,
+        }
+    }
+
+    /// Amount Shaders and UI are Faded Out towards the Desktop
+    ///
+    /// Ramps from 1 to 0 during the fade-in and back to 1 while unlocking.
+    fn fade_amount(&self) -> f32 {
+        let fade_in = 1.0 - progress(self.fade_start, self.fade_in);
+        fade_in.max(self.unlock_progress())
+    }
+
+    /// Track Mouse State following Shadertoy `iMouse` Conventions
+    ///
+    /// `xy` follows the cursor while the left button is held and `zw`
+    /// holds the last click position (negated once released).
+    fn mouse_event(&mut self, event: &mouse::Event) {
+        let flip = |p: Point| [p.x, self.height as f32 - p.y];
+        match event {
+            mouse::Event::CursorMoved { position } => {
+                self.cursor = *position;
+                if self.mouse[2] > 0.0 {
+                    let [x, y] = flip(*position);
+                    self.mouse[0] = x;
+                    self.mouse[1] = y;
+                }
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                let [x, y] = flip(self.cursor);
+                self.mouse = [x, y, x, y];
+            }
+            mouse::Event::ButtonReleased(mouse::Button::Left) => {
+                self.mouse[2] = -self.mouse[2].abs();
+                self.mouse[3] = -self.mouse[3].abs();
+            }
+            _ => {}
         }
     }
 }
 
-/// Complete Graphics Rendering State Tracker
-pub struct State<'a> {
-    format: wgpu::TextureFormat,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    render_pipeline: wgpu::RenderPipeline,
-    bind_group: wgpu::BindGroup,
-    surface: wgpu::Surface<'a>,
-    context: RenderContext,
-    iced: Option<IcedState>,
+/// Double-Buffered Render Target of a Shader Pack Buffer Pass
+///
+/// The front texture holds the most recently completed frame and is read
+/// by inputs while the back texture is rendered into.
+struct PassBuffer {
+    views: [wgpu::TextureView; 2],
+    front: usize,
+    width: u32,
+    height: u32,
 }
 
-impl<'a> State<'a> {
-    /// Build Wgpu State Instance
-    pub async fn new(
-        conn: &Connection,
-        rgba: Background,
-        shader: &str,
-        lock: bool,
-        lock_surface: &SessionLockSurface,
-    ) -> Result<Self> {
-        // spawn wgpu instance
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        // build surface from raw wayland handles
-        let raw_display_handle = RawDisplayHandle::Wayland(WaylandDisplayHandle::new(
-            NonNull::new(conn.backend().display_ptr() as *mut _).unwrap(),
-        ));
-        let raw_window_handle = RawWindowHandle::Wayland(WaylandWindowHandle::new(
-            NonNull::new(lock_surface.wl_surface().id().as_ptr() as *mut _).unwrap(),
-        ));
-        let surface = unsafe {
-            instance
-                .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
-                    raw_display_handle,
-                    raw_window_handle,
+impl PassBuffer {
+    fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let view = || {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Pass Buffer"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: BUFFER_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
                 })
-                .context("wgpu - failed to adopt surface handle")?
+                .create_view(&wgpu::TextureViewDescriptor::default())
         };
-        // build device/queue from adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
+        Self {
+            views: [view(), view()],
+            front: 0,
+            width,
+            height,
+        }
+    }
+}
+
+/// Encode a Fullscreen Draw of the Pipeline into the Target View
+fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    view: &wgpu::TextureView,
+) {
+    let render_pass_desc = wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    };
+    let mut render_pass = encoder.begin_render_pass(&render_pass_desc);
+
+    // render shaders with uniforms and constants
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..6, 0..1);
+}
+
+/// Bind Groups of a Pass Keyed by the Front Textures of its Input Buffers
+type BindGroupCache = BTreeMap<[usize; MAX_CHANNELS], wgpu::BindGroup>;
+
+/// Running Shader Pack alongside its Uniforms and Buffer Render Targets
+struct PackState {
+    pack: Arc<CompiledPack>,
+    background: Arc<wgpu::TextureView>,
+    uniforms: Vec<wgpu::Buffer>,
+    buffers: BTreeMap<Target, PassBuffer>,
+    bind_groups: Vec<BindGroupCache>,
+}
+
+impl PackState {
+    fn new(
+        device: &wgpu::Device,
+        pack: Arc<CompiledPack>,
+        background: Arc<wgpu::TextureView>,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let mut state = Self {
+            pack,
+            background,
+            uniforms: Vec::new(),
+            buffers: BTreeMap::new(),
+            bind_groups: Vec::new(),
+        };
+        state.resize(device, width, height);
+        state
+    }
+
+    /// Rebuild Per-Pass Uniform Buffers and Buffer Render Targets
+    ///
+    /// Buffer contents are reset whenever the output size changes.
+    fn resize(&mut self, device: &wgpu::Device, width: usize, height: usize) {
+        self.uniforms = self
+            .pack
+            .passes
+            .iter()
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Frame Uniforms"),
+                    size: UNIFORMS_SIZE,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
             })
-            .await
-            .context("wgpu - adapter init failed")?;
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::PUSH_CONSTANTS,
-                    required_limits: wgpu::Limits {
-                        max_push_constant_size: PUSH_CONSTANTS_SIZE,
-                        ..Default::default()
-                    },
+            .collect();
+        self.buffers = self
+            .pack
+            .passes
+            .iter()
+            .filter(|pass| pass.output != Target::Image)
+            .map(|pass| {
+                let width = ((width as f32 * pass.scale) as u32).max(1);
+                let height = ((height as f32 * pass.scale) as u32).max(1);
+                (pass.output, PassBuffer::new(device, width, height))
+            })
+            .collect();
+        self.bind_groups = self.pack.passes.iter().map(|_| BTreeMap::new()).collect();
+    }
+
+    /// Encode Every Pass of the Shader Pack (Image Pass into the Given View)
+    fn render(
+        &mut self,
+        graphics: &GraphicsContext,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: FrameUniforms,
+        view: &wgpu::TextureView,
+    ) {
+        for (n, pass) in self.pack.passes.iter().enumerate() {
+            // update frame uniforms for the pass target (buffers are never faded)
+            let target = self.buffers.get(&pass.output);
+            let uniforms = match target {
+                Some(buffer) => FrameUniforms {
+                    resolution: [buffer.width as f32, buffer.height as f32, 1.0],
+                    fade_amount: 0.0,
+                    buffer_pass: 1.0,
+                    ..frame
                 },
-                None,
-            )
-            .await
-            .context("wgpu - failed to create device/queue")?;
-        // compile shader components
-        let compiler = shaderc::Compiler::new().context("shaderc - compiler init failed")?;
-        log::debug!("shaderc - compiling vertex shader");
-        let vs_spirv = compiler
-            .compile_into_spirv(
-                VERT_SHADER,
-                shaderc::ShaderKind::Vertex,
-                "shader.vert",
-                "main",
-                None,
-            )
-            .context("shaderc - failed to compile vertex shader")?;
-        // attempt to compile fragment shader
-        log::debug!("shaderc - compiling fragment shader");
-        let fs_spirv = compiler
-            .compile_into_spirv(
-                shader,
-                shaderc::ShaderKind::Fragment,
-                "shader.frag",
-                "main",
-                None,
-            )
-            .unwrap_or_else(|err| {
-                log::error!("failed to compile fragment shader: {err:?}");
-                compiler
-                    .compile_into_spirv(
-                        FRAG_SHADER,
-                        shaderc::ShaderKind::Fragment,
-                        "shader.frag",
-                        "main",
-                        None,
-                    )
-                    .expect("fallback error shader compilation failed")
+                None => frame,
+            };
+            graphics
+                .queue
+                .write_buffer(&self.uniforms[n], 0, bytemuck::bytes_of(&uniforms));
+            // bind groups only change when the buffers read by the pass swap
+            let input = |i: usize| {
+                pass.inputs
+                    .get(i)
+                    .and_then(|input| input.target())
+                    .and_then(|target| self.buffers.get(&target))
+            };
+            let fronts: [usize; MAX_CHANNELS] =
+                std::array::from_fn(|i| input(i).map(|buffer| buffer.front).unwrap_or(0));
+            let bind_group = self.bind_groups[n].entry(fronts).or_insert_with(|| {
+                // bind pass inputs (unused channels default to the screenshot)
+                let channels: [&wgpu::TextureView; MAX_CHANNELS] = std::array::from_fn(|i| {
+                    input(i)
+                        .map(|buffer| &buffer.views[buffer.front])
+                        .unwrap_or(&self.background)
+                });
+                // bind shader textures (unset slots default to the screenshot)
+                let textures: [&wgpu::TextureView; MAX_TEXTURES] = std::array::from_fn(|i| {
+                    self.pack.textures[i].as_ref().unwrap_or(&self.background)
+                });
+                graphics.bind_group(
+                    &self.background,
+                    &self.uniforms[n],
+                    channels,
+                    &self.pack.params,
+                    textures,
+                )
             });
-        let vs_data = wgpu::util::make_spirv(vs_spirv.as_binary_u8());
-        let fs_data = wgpu::util::make_spirv(fs_spirv.as_binary_u8());
-        let vs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Vertex Shader"),
-            source: vs_data,
-        });
-        let fs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Fragment Shader"),
-            source: fs_data,
-        });
-        // build bind group
-        log::debug!("wgpu - building bind group");
-        let screenshot = super::screenshot::screenshot(rgba, &device, &queue);
-        let screenshot_view = screenshot.create_view(&wgpu::TextureViewDescriptor::default());
-        let screenshot_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    // This should match the filterable field of the
-                    // corresponding Texture entry above.
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-            label: Some("texture_bind_group_layout"),
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&screenshot_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&screenshot_sampler),
+            // buffer passes render into their back texture
+            let target_view = match target {
+                Some(buffer) => &buffer.views[1 - buffer.front],
+                None => view,
+            };
+            draw(encoder, &pass.pipeline, bind_group, target_view);
+            // later passes and the next frame read the completed buffer
+            if let Some(buffer) = self.buffers.get_mut(&pass.output) {
+                buffer.front = 1 - buffer.front;
+            }
+        }
+    }
+}
+
+/// Build Offscreen Targets the Crossfaded Packs are Rendered into
+fn crossfade_targets(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: usize,
+    height: usize,
+) -> [wgpu::TextureView; 2] {
+    std::array::from_fn(|_| {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Crossfade Target"),
+                size: wgpu::Extent3d {
+                    width: (width as u32).max(1),
+                    height: (height as u32).max(1),
+                    depth_or_array_layers: 1,
                 },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
-        // build rendering pipeline
-        log::debug!("wgpu - building rendering pipeline");
-        let capabilities = surface.get_capabilities(&adapter);
-        let texture_format = capabilities.formats[0];
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[wgpu::PushConstantRange {
-                    stages: wgpu::ShaderStages::FRAGMENT,
-                    range: 0..PUSH_CONSTANTS_SIZE,
-                }],
-            });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &vs_module,
-                entry_point: "main",
-                buffers: &[],
-            },
-            multiview: None,
-            fragment: Some(wgpu::FragmentState {
-                module: &fs_module,
-                entry_point: "main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: texture_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-                unclipped_depth: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-        });
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    })
+}
+
+/// Outgoing Shader Pack being Blended into the Current Pack
+struct Crossfade {
+    previous: PackState,
+    start: Instant,
+    duration: Duration,
+    uniforms: wgpu::Buffer,
+    views: [wgpu::TextureView; 2],
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+/// Complete Graphics Rendering State Tracker
+pub struct State<'a> {
+    graphics: Arc<GraphicsContext>,
+    current: PackState,
+    crossfade: Option<Crossfade>,
+    surface: wgpu::Surface<'a>,
+    context: RenderContext,
+    iced: Option<IcedState>,
+    frame_scheduled: bool,
+    last_frame: Instant,
+    failed_frames: u32,
+}
+
+impl<'a> State<'a> {
+    /// Build Per-Output State from Shared Graphics Context
+    pub fn new(
+        graphics: Arc<GraphicsContext>,
+        surface: wgpu::Surface<'a>,
+        pack: Arc<CompiledPack>,
+        background: wgpu::TextureView,
+        auth: Arc<Mutex<AuthController>>,
+        settings: &Settings,
+    ) -> Self {
         // spawn iced components
-        let iced = match lock {
-            true => Some(IcedState::new(&adapter, &device, &queue, texture_format)),
+        let shader_error = graphics.shader_error();
+        let iced = match settings.lock || settings.watch || shader_error.is_some() {
+            true => Some(iced_state(&graphics, auth, settings)),
             false => None,
         };
-        // return compiled state object
-        Ok(Self {
-            format: texture_format,
-            device,
-            queue,
-            render_pipeline,
-            bind_group,
+        let context = RenderContext::new(&settings.animation);
+        let current = PackState::new(
+            &graphics.device,
+            pack,
+            Arc::new(background),
+            context.width,
+            context.height,
+        );
+        Self {
+            graphics,
+            current,
+            crossfade: None,
             surface,
-            context: RenderContext::new(),
+            context,
             iced,
-        })
+            frame_scheduled: false,
+            last_frame: Instant::now(),
+            failed_frames: 0,
+        }
     }
 
     /// Configure Rendering Viewports and Surfaces with Width/Height
     pub fn configure(&mut self, width: u32, height: u32) {
         log::debug!("wgpu - configuing surface ({width}/{height})");
+        let format = self.graphics.format;
+        self.context.width = width as usize;
+        self.context.height = height as usize;
+        self.configure_surface();
+        let device = &self.graphics.device;
+        self.current
+            .resize(device, self.context.width, self.context.height);
+        if let Some(fade) = self.crossfade.as_mut() {
+            let (width, height) = (self.context.width, self.context.height);
+            fade.previous.resize(device, width, height);
+            fade.views = crossfade_targets(device, format, width, height);
+            fade.bind_group = None;
+        }
+        if let Some(iced) = self.iced.as_mut() {
+            let mut renderer = self.graphics.renderer();
+            iced.configure(width, height, &mut renderer);
+        }
+    }
+
+    /// (Re)Configure the Wgpu Surface for the Current Viewport Size
+    fn configure_surface(&mut self) {
+        let format = self.graphics.format;
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: self.format,
-            view_formats: vec![self.format],
+            format,
+            view_formats: vec![format],
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            width,
-            height,
+            width: self.context.width as u32,
+            height: self.context.height as u32,
             desired_maximum_frame_latency: 2,
             // Wayland is inherently a mailbox system.
             present_mode: wgpu::PresentMode::Mailbox,
         };
-        self.context.width = width as usize;
-        self.context.height = height as usize;
-        self.surface.configure(&self.device, &surface_config);
-        if let Some(iced) = self.iced.as_mut() {
-            iced.configure(width, height);
-        }
+        self.surface
+            .configure(&self.graphics.device, &surface_config);
     }
 
     /// Pass Keyboard Event to Iced UI Instance
@@ -306,73 +526,207 @@ impl<'a> State<'a> {
         }
     }
 
-    /// Pass Mouse Event to Iced UI Instance
+    /// Pass Mouse Event to Shader Uniforms and Iced UI Instance
     #[inline]
-    pub fn mouse_event(&mut self, event: iced_runtime::core::mouse::Event) {
+    pub fn mouse_event(&mut self, event: mouse::Event) {
+        self.context.mouse_event(&event);
         if let Some(iced) = self.iced.as_mut() {
             iced.mouse_event(event);
         }
     }
 
-    /// Check if UI has Completed Authentication
+    /// Swap in a Recompiled Shader Pack
+    #[inline]
+    pub fn set_pack(&mut self, pack: Arc<CompiledPack>) {
+        self.crossfade(Some(pack), None, Duration::ZERO);
+    }
+
+    /// Blend into a New Shader Pack and/or Background over the Duration
+    ///
+    /// Unspecified components are carried over from the current pack.
+    pub fn crossfade(
+        &mut self,
+        pack: Option<Arc<CompiledPack>>,
+        background: Option<Arc<wgpu::TextureView>>,
+        duration: Duration,
+    ) {
+        let device = &self.graphics.device;
+        let (width, height) = (self.context.width, self.context.height);
+        let pack = pack.unwrap_or_else(|| Arc::clone(&self.current.pack));
+        let background = background.unwrap_or_else(|| Arc::clone(&self.current.background));
+        let next = PackState::new(device, pack, background, width, height);
+        let previous = std::mem::replace(&mut self.current, next);
+        self.crossfade = match duration.is_zero() {
+            true => None,
+            false => Some(Crossfade {
+                previous,
+                start: Instant::now(),
+                duration,
+                uniforms: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Crossfade Uniforms"),
+                    size: UNIFORMS_SIZE,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                views: crossfade_targets(device, self.graphics.format, width, height),
+                bind_group: None,
+            }),
+        };
+    }
+
+    /// Display or Clear Shader Compiler Diagnostics
+    #[inline]
+    pub fn set_shader_error(&mut self, error: Option<String>) {
+        if let Some(iced) = self.iced.as_mut() {
+            iced.set_shader_error(error);
+        }
+    }
+
+    /// Escalate the Screensaver into the Password UI in Place
+    ///
+    /// Any existing ui is rebuilt in lock mode for the configured surface.
+    pub fn lock(&mut self, auth: Arc<Mutex<AuthController>>, settings: &Settings) {
+        let mut iced = iced_state(&self.graphics, auth, settings);
+        let mut renderer = self.graphics.renderer();
+        let (width, height) = self.size();
+        iced.configure(width, height, &mut renderer);
+        self.iced = Some(iced);
+    }
+
+    /// Allow or Prevent the UI from Editing the Shared Password
+    #[inline]
+    pub fn set_active(&mut self, active: bool) {
+        if let Some(iced) = self.iced.as_mut() {
+            iced.set_active(active);
+        }
+    }
+
+    /// Configured Surface Size
     #[inline]
-    pub fn is_authenticated(&self) -> bool {
-        self.iced
-            .as_ref()
-            .map(|iced| iced.is_authenticated())
-            .unwrap_or(false)
+    pub fn size(&self) -> (u32, u32) {
+        (self.context.width as u32, self.context.height as u32)
+    }
+
+    /// Check if Rendering has Repeatedly Failed to Present Frames
+    /// or the Shared Device can no Longer be Used
+    #[inline]
+    pub fn is_failing(&self) -> bool {
+        self.failed_frames >= MAX_FAILED_FRAMES || self.graphics.is_lost()
+    }
+
+    /// Begin Fading Out the Output before Unlocking
+    #[inline]
+    pub fn fade_out(&mut self) {
+        self.context.unlock_start.get_or_insert_with(Instant::now);
+    }
+
+    /// Check if a Frame Callback is Pending for the Surface
+    #[inline]
+    pub fn frame_scheduled(&self) -> bool {
+        self.frame_scheduled
+    }
+
+    /// Mark Frame Callback as Requested for the Surface
+    #[inline]
+    pub fn schedule_frame(&mut self) {
+        self.frame_scheduled = true;
+    }
+
+    /// Complete Pending Frame Callback
+    ///
+    /// Returns the remaining delay if rendering now would exceed
+    /// the frame interval, in which case the frame stays scheduled.
+    pub fn frame_done(&mut self, interval: Option<Duration>) -> Option<Duration> {
+        let delay = interval
+            .and_then(|interval| interval.checked_sub(self.last_frame.elapsed()))
+            .filter(|delay| !delay.is_zero());
+        self.frame_scheduled = delay.is_some();
+        delay
     }
 
     /// Complete Frame Rendering of Entire Graphics Scene
-    pub fn render(&mut self) {
+    ///
+    /// Returns false if no frame could be presented.
+    pub fn render(&mut self) -> bool {
+        self.last_frame = Instant::now();
+        if self.graphics.is_lost() {
+            return false;
+        }
+        self.context.advance();
         // prepare texture from surface
         let surface_texture = match self.surface.get_current_texture() {
             Ok(texture) => texture,
+            // outdated or lost surfaces are expected and recover once reconfigured
+            Err(err @ (wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost)) => {
+                log::warn!("wgpu - reconfiguring surface: {err:?}");
+                self.configure_surface();
+                return false;
+            }
             Err(err) => {
                 log::error!("wgpu - failed to acquire texture: {err:?}");
-                return;
+                self.failed_frames += 1;
+                return false;
             }
         };
         let texture_view = surface_texture
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-        // build renderpass for texture
-        let mut encoder = self
+        // build command encoder for every pass
+        let graphics = &self.graphics;
+        let mut encoder = graphics
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        {
-            let render_pass_desc = wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            };
-            let mut render_pass = encoder.begin_render_pass(&render_pass_desc);
-
-            // render shaders with uniforms and constants
-            let constants = FrameUniforms::new(&self.context);
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_push_constants(
-                wgpu::ShaderStages::FRAGMENT,
-                0,
-                bytemuck::bytes_of(&constants),
-            );
-            render_pass.draw(0..6, 0..1);
+        let frame = FrameUniforms::new(&self.context, self.iced.as_ref());
+        match self.crossfade.as_mut() {
+            None => self
+                .current
+                .render(graphics, &mut encoder, frame, &texture_view),
+            Some(fade) => {
+                // render both packs offscreen and blend them onto the surface
+                fade.previous
+                    .render(graphics, &mut encoder, frame, &fade.views[0]);
+                self.current
+                    .render(graphics, &mut encoder, frame, &fade.views[1]);
+                let uniforms = FrameUniforms {
+                    fade_amount: progress(fade.start, fade.duration),
+                    ..frame
+                };
+                graphics
+                    .queue
+                    .write_buffer(&fade.uniforms, 0, bytemuck::bytes_of(&uniforms));
+                let background = &*self.current.background;
+                let bind_group = fade.bind_group.get_or_insert_with(|| {
+                    graphics.bind_group(
+                        background,
+                        &fade.uniforms,
+                        [&fade.views[0], &fade.views[1], background, background],
+                        &self.current.pack.params,
+                        [background; MAX_TEXTURES],
+                    )
+                });
+                draw(&mut encoder, &graphics.crossfade, bind_group, &texture_view);
+            }
+        }
+        // drop the outgoing pack once it has fully faded out
+        if (self.crossfade.as_ref()).is_some_and(|fade| fade.start.elapsed() >= fade.duration) {
+            log::debug!("wgpu - crossfade complete");
+            self.crossfade = None;
         }
         // submit rendering for final generation
         if let Some(iced) = self.iced.as_mut() {
-            iced.render(&self.device, &self.queue, &mut encoder, &texture_view);
+            let mut renderer = graphics.renderer();
+            iced.render(
+                &graphics.device,
+                &graphics.queue,
+                &mut renderer,
+                &mut encoder,
+                &texture_view,
+                1.0 - self.context.fade_amount(),
+            );
         }
-        self.queue.submit(Some(encoder.finish()));
+        graphics.queue.submit(Some(encoder.finish()));
         surface_texture.present();
+        self.failed_frames = 0;
+        true
     }
 }
@@ -1,35 +1,142 @@
 //! Styling Definitions for Iced Widgets
-use iced_wgpu::core::{Shadow, Vector};
+use std::borrow::Cow;
+use std::sync::Once;
+
+use iced_wgpu::core::{Font, Shadow, Vector};
 use iced_widget::core::{Background, Border, Color};
 use iced_widget::{button, container, text_input, theme, Theme};
 
+use dynlock_lib::{Rgba, ThemeColors, ThemeConfig};
+
+/// Color Used to Signal a Failed Authentication Attempt
+const WRONG: Color = Color::from_rgb(0.9, 0.25, 0.25);
+
+/// Convert Configured Color into an Iced Color
+#[inline]
+fn color(rgba: Rgba) -> Color {
+    let [r, g, b, a] = rgba.0;
+    Color::from_rgba(r, g, b, a)
+}
+
+/// Load Configured Font File into the Shared Font System
+///
+/// The font is only loaded once even when both renderers request it.
+pub fn load_font(theme: &ThemeConfig) {
+    static LOADED: Once = Once::new();
+    let Some(path) = theme.font_file.as_ref() else {
+        return;
+    };
+    LOADED.call_once(|| {
+        let path = shellexpand::tilde(path).to_string();
+        match std::fs::read(&path) {
+            Ok(data) => {
+                log::debug!("iced - loading font file {path:?}");
+                let mut fonts = iced_wgpu::graphics::text::font_system()
+                    .write()
+                    .expect("font system lock failed");
+                fonts.load_font(Cow::Owned(data));
+            }
+            Err(err) => log::error!("iced - failed to read font file {path:?}: {err}"),
+        }
+        if theme.font.is_none() {
+            log::warn!("iced - font_file is set without a font family name to use");
+        }
+    });
+}
+
+/// Resolve Configured Font Family
+///
+/// Iced requires static family names so the name is leaked once.
+pub fn font(theme: &ThemeConfig) -> Font {
+    match theme.font.as_ref() {
+        Some(name) => Font::with_name(Box::leak(name.clone().into_boxed_str())),
+        None => Font::default(),
+    }
+}
+
+/// Theme Values Shared by Lock Menu Styles
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    colors: ThemeColors,
+    border: f32,
+    radius: f32,
+}
+
+impl Palette {
+    pub fn new(theme: &ThemeConfig) -> Self {
+        Self {
+            colors: theme.colors,
+            border: theme.border_width,
+            radius: theme.radius,
+        }
+    }
+}
+
+/// Scale Color Transparency by the Given Opacity
+#[inline]
+fn fade(color: Color, opacity: f32) -> Color {
+    Color {
+        a: color.a * opacity,
+        ..color
+    }
+}
+
 /// Generate Password TextInput Theme
-pub fn password() -> theme::TextInput {
-    theme::TextInput::Custom(Box::new(PasswordStyle {}))
+pub fn password(palette: Palette, wrong: bool, opacity: f32) -> theme::TextInput {
+    theme::TextInput::Custom(Box::new(PasswordStyle {
+        palette,
+        wrong,
+        opacity,
+    }))
 }
 
 /// Generate Show/Hide Button Theme
-pub fn show() -> theme::Button {
-    theme::Button::Custom(Box::new(ButtonStyle {}))
+pub fn show(palette: Palette, opacity: f32) -> theme::Button {
+    theme::Button::Custom(Box::new(ButtonStyle { palette, opacity }))
 }
 
 /// Generate MenuBox Container Theme
-pub fn menubox() -> theme::Container {
-    theme::Container::Custom(Box::new(MenuBoxStyle {}))
+pub fn menubox(palette: Palette, wrong: bool, opacity: f32) -> theme::Container {
+    theme::Container::Custom(Box::new(MenuBoxStyle {
+        palette,
+        wrong,
+        opacity,
+    }))
+}
+
+/// Generate Shader Diagnostics Container Theme
+pub fn diagnostics(opacity: f32) -> theme::Container {
+    theme::Container::Custom(Box::new(DiagnosticsStyle { opacity }))
 }
 
 /// Password TextInput Styling
-struct PasswordStyle {}
+struct PasswordStyle {
+    palette: Palette,
+    wrong: bool,
+    opacity: f32,
+}
 
 impl text_input::StyleSheet for PasswordStyle {
     type Style = Theme;
 
     fn active(&self, _style: &Self::Style) -> text_input::Appearance {
-        let blank = Color::TRANSPARENT;
-        let text_color = Color::WHITE;
+        let colors = &self.palette.colors;
+        let text_color = self.value_color(_style);
+        let border = match self.wrong {
+            true => Border {
+                color: fade(color(colors.wrong), self.opacity),
+                width: self.palette.border.max(1.0),
+                radius: self.palette.radius.max(2.0).into(),
+            },
+            false => Border {
+                color: fade(color(colors.border), self.opacity),
+                width: self.palette.border,
+                radius: self.palette.radius.into(),
+            },
+        };
         text_input::Appearance {
-            background: Background::Color(blank),
-            border: Border::default(),
+            background: Background::Color(fade(color(colors.input), self.opacity)),
+            border,
             icon_color: text_color,
         }
     }
@@ -43,21 +150,28 @@ impl text_input::StyleSheet for PasswordStyle {
         self.active(style)
     }
     fn value_color(&self, _style: &Self::Style) -> Color {
-        Color::WHITE
+        let value = match self.wrong {
+            true => self.palette.colors.wrong,
+            false => self.palette.colors.text,
+        };
+        fade(color(value), self.opacity)
     }
     fn disabled_color(&self, _style: &Self::Style) -> Color {
-        Color::BLACK
+        fade(Color::BLACK, self.opacity)
     }
     fn selection_color(&self, _style: &Self::Style) -> Color {
-        Color::WHITE
+        fade(color(self.palette.colors.selection), self.opacity)
     }
-    fn placeholder_color(&self, _style: &Self::Style) -> Color {
-        Color::WHITE
+    fn placeholder_color(&self, style: &Self::Style) -> Color {
+        self.value_color(style)
     }
 }
 
 /// Password Show/Hide Button Styling
-struct ButtonStyle {}
+struct ButtonStyle {
+    palette: Palette,
+    opacity: f32,
+}
 
 impl button::StyleSheet for ButtonStyle {
     type Style = Theme;
@@ -68,7 +182,7 @@ impl button::StyleSheet for ButtonStyle {
             background: None,
             border: Border::default(),
             shadow: Shadow::default(),
-            text_color: Color::WHITE,
+            text_color: fade(color(self.palette.colors.text), self.opacity),
         }
     }
     fn pressed(&self, style: &Self::Style) -> button::Appearance {
@@ -77,17 +191,52 @@ impl button::StyleSheet for ButtonStyle {
 }
 
 /// MenuBox Container Styling
-struct MenuBoxStyle {}
+struct MenuBoxStyle {
+    palette: Palette,
+    wrong: bool,
+    opacity: f32,
+}
 
 impl container::StyleSheet for MenuBoxStyle {
     type Style = Theme;
 
     fn appearance(&self, _style: &Self::Style) -> container::Appearance {
-        let bg = iced_widget::core::Color::from_rgba8(1, 4, 11, 0.7);
-        let text = iced_widget::core::Color::WHITE;
+        let colors = &self.palette.colors;
+        let bg = match self.wrong {
+            true => colors.wrong_background,
+            false => colors.background,
+        };
+        container::Appearance {
+            background: Some(Background::Color(fade(color(bg), self.opacity))),
+            text_color: Some(fade(color(colors.text), self.opacity)),
+            border: Border {
+                color: fade(color(colors.border), self.opacity),
+                width: self.palette.border,
+                radius: self.palette.radius.into(),
+            },
+            ..container::Appearance::default()
+        }
+    }
+}
+
+/// Shader Diagnostics Container Styling
+struct DiagnosticsStyle {
+    opacity: f32,
+}
+
+impl container::StyleSheet for DiagnosticsStyle {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        let bg = Color::from_rgba8(1, 4, 11, 0.85);
         container::Appearance {
-            background: Some(iced_widget::core::Background::Color(bg)),
-            text_color: Some(text),
+            background: Some(Background::Color(fade(bg, self.opacity))),
+            text_color: Some(fade(WRONG, self.opacity)),
+            border: Border {
+                color: fade(WRONG, self.opacity),
+                width: 1.0,
+                radius: 2.0.into(),
+            },
             ..container::Appearance::default()
         }
     }
@@ -1,23 +1,28 @@
 //! Iced UI Implementation
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::{Duration, SystemTime};
 
 use iced_runtime::command::Action;
 use iced_runtime::core::keyboard;
-use iced_runtime::{program::State, Debug, Font};
+use iced_runtime::{program::State, Debug};
 
-use iced_wgpu::core::alignment::Vertical;
+use iced_wgpu::core::alignment::{Horizontal, Vertical};
 use iced_wgpu::core::Point;
-use iced_wgpu::core::{mouse, renderer, Clipboard, Color, Event, Length, Pixels, Size};
+use iced_wgpu::core::{
+    mouse, renderer, Alignment, Clipboard, Color, Element, Event, Font, Length, Padding, Size,
+};
 use iced_wgpu::graphics::Viewport;
-use iced_wgpu::Settings;
-use iced_wgpu::{wgpu, Backend, Renderer};
+use iced_wgpu::{wgpu, Renderer};
 
 use iced_widget::{container, Column, Row, Theme};
 
 use super::style;
+use crate::auth::AuthController;
+use crate::user;
+use dynlock_lib::{Align, Anchor, LayoutConfig, ThemeConfig, Widget};
 
-const CAPS_LOCK_ICON: &'static [u8] = include_bytes!("../../icons/caps-lock.png");
+pub const CAPS_LOCK_ICON: &'static [u8] = include_bytes!("../../icons/caps-lock.png");
 const HIDE_ICON: &'static [u8] = include_bytes!("../../icons/hide.png");
 const SHOW_ICON: &'static [u8] = include_bytes!("../../icons/show.png");
 
@@ -27,6 +32,39 @@ const CTRL: keyboard::Key = keyboard::Key::Named(keyboard::key::Named::Control);
 const ESCAPE: keyboard::Key = keyboard::Key::Named(keyboard::key::Named::Escape);
 const CAPS_LOCK: keyboard::Key = keyboard::Key::Named(keyboard::key::Named::CapsLock);
 const HOLD_KEY_TIMEOUT: Duration = Duration::from_millis(200);
+const SHAKE_DURATION: Duration = Duration::from_millis(400);
+const FAILURE_PULSE_DURATION: Duration = Duration::from_millis(1000);
+
+/// Lock and Authentication State Exposed to Shaders
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LockStatus {
+    pub password_length: f32,
+    pub keystroke_time: f32,
+    pub caps_lock: f32,
+    pub authenticating: f32,
+    pub failures: f32,
+    pub failure_pulse: f32,
+}
+
+/// Fraction of Duration Elapsed since the Specified Time (Clamped to 0-1)
+fn progress(since: Option<SystemTime>, duration: Duration) -> Option<f32> {
+    let elapsed = SystemTime::now().duration_since(since?).unwrap_or_default();
+    Some((elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0))
+}
+
+/// Resolve Configured Layout from Custom then Builtin Layouts
+fn layout(theme: &ThemeConfig) -> LayoutConfig {
+    let name = theme.layout.as_str();
+    theme
+        .layouts
+        .get(name)
+        .cloned()
+        .or_else(|| LayoutConfig::builtin(name))
+        .unwrap_or_else(|| {
+            log::warn!("iced - unknown layout {name:?}, using classic");
+            LayoutConfig::classic()
+        })
+}
 
 /// Lockscreen UI Implementation
 pub struct UI {
@@ -34,12 +72,19 @@ pub struct UI {
     caps_img: iced_widget::image::Handle,
     show_img: iced_widget::image::Handle,
     hide_img: iced_widget::image::Handle,
-    username: String,
-    password: String,
     caps_lock: bool,
     hide_input: bool,
-    auth_thread: Option<std::thread::JoinHandle<()>>,
-    authenticated: Arc<Mutex<bool>>,
+    auth: Arc<Mutex<AuthController>>,
+    active: bool,
+    opacity: f32,
+    lock: bool,
+    shader_error: Option<String>,
+    theme: ThemeConfig,
+    layout: LayoutConfig,
+    palette: style::Palette,
+    font: Font,
+    realname: String,
+    avatar: Option<iced_widget::image::Handle>,
 }
 
 #[derive(Debug, Clone)]
@@ -50,10 +95,21 @@ pub enum Message {
     Reset,
     ToggleHide(Option<bool>),
     CapsLock(bool),
+    Active(bool),
+    Opacity(f32),
+    ShaderError(Option<String>),
 }
 
 impl UI {
-    pub fn new() -> Self {
+    pub fn new(
+        auth: Arc<Mutex<AuthController>>,
+        active: bool,
+        theme: ThemeConfig,
+        font: Font,
+        avatar: Option<iced_widget::image::Handle>,
+        lock: bool,
+        shader_error: Option<String>,
+    ) -> Self {
         let input_id = iced_widget::text_input::Id::unique();
         let caps_img = iced_widget::image::Handle::from_memory(CAPS_LOCK_ICON);
         let hide_img = iced_widget::image::Handle::from_memory(HIDE_ICON);
@@ -63,125 +119,251 @@ impl UI {
             caps_img,
             show_img,
             hide_img,
-            username: whoami::username(),
-            password: "".to_owned(),
             hide_input: true,
             caps_lock: false,
-            auth_thread: None,
-            authenticated: Arc::new(Mutex::new(false)),
+            auth,
+            active,
+            opacity: 1.0,
+            lock,
+            shader_error,
+            palette: style::Palette::new(&theme),
+            layout: layout(&theme),
+            theme,
+            font,
+            realname: user::realname(),
+            avatar,
         }
     }
-    /// Check if Authentication Thread is Running
+    /// Access Authentication State Shared between Outputs
     #[inline]
-    fn auth_running(&self) -> bool {
-        self.auth_thread
-            .as_ref()
-            .map(|t| !t.is_finished())
-            .unwrap_or(false)
+    fn auth(&self) -> MutexGuard<'_, AuthController> {
+        self.auth.lock().expect("auth controller lock failed")
     }
-    /// Spawn Authentication Thread (if not already running)
-    fn start_authenticate(&mut self) {
-        // skip authenticating if already in progress
-        if self.auth_running() {
-            log::error!("requested auth while already authenticating");
-            return;
+    /// Horizontal Offset of Shake Animation after a Failed Attempt
+    fn shake_offset(&self) -> f32 {
+        let elapsed = self
+            .auth()
+            .failed_at()
+            .and_then(|failed_at| SystemTime::now().duration_since(failed_at).ok())
+            .unwrap_or(SHAKE_DURATION);
+        if elapsed >= SHAKE_DURATION {
+            return 0.0;
         }
-        // spawn thread to complete login attempt in background
-        let username = self.username.to_owned();
-        let password = self.password.to_owned();
-        let authenticated = Arc::clone(&self.authenticated);
-        self.auth_thread = Some(std::thread::spawn(move || {
-            // attempt login via pam
-            let mut client =
-                pam::Client::with_password("system-auth").expect("Failed to init PAM client.");
-            client
-                .conversation_mut()
-                .set_credentials(username, password);
-            let auth_result = client.authenticate().is_ok();
-            // update authentication status
-            let mut auth = authenticated.lock().expect("mutex lock failed");
-            *auth = auth_result;
-        }));
+        let progress = elapsed.as_secs_f32() / SHAKE_DURATION.as_secs_f32();
+        (progress * 6.0 * std::f32::consts::PI).sin() * 10.0 * (1.0 - progress)
     }
-    /// Check if Successfully Authenticated
-    #[inline]
-    fn is_authenticated(&self) -> bool {
-        *self.authenticated.lock().expect("mutex lock failed")
+    /// Collect Lock State Exposed to Shaders
+    fn status(&self) -> LockStatus {
+        let auth = self.auth();
+        let keystroke = SystemTime::now()
+            .duration_since(auth.typed_at())
+            .unwrap_or_default();
+        let pulse = progress(auth.failed_at(), FAILURE_PULSE_DURATION).unwrap_or(1.0);
+        LockStatus {
+            password_length: auth.password().chars().count() as f32,
+            keystroke_time: keystroke.as_secs_f32(),
+            caps_lock: self.caps_lock as u32 as f32,
+            authenticating: auth.is_verifying() as u32 as f32,
+            failures: auth.failures() as f32,
+            failure_pulse: 1.0 - pulse,
+        }
     }
 }
 
-impl iced_runtime::Program for UI {
-    type Theme = Theme;
-    type Message = Message;
-    type Renderer = iced_wgpu::Renderer;
+impl UI {
+    /// Shader Compiler Diagnostics Overlay
+    fn error_view<'a>(&self, error: &'a str) -> Element<'a, Message, Theme, Renderer> {
+        let title = iced_widget::text("Shader compilation failed").size(16.0);
+        let diagnostics = iced_widget::text(error).font(Font::MONOSPACE).size(12.0);
+        let overlay = container(Column::new().push(title).push(diagnostics).spacing(5))
+            .padding(10)
+            .width(Length::Fill)
+            .style(style::diagnostics(self.opacity));
+        container(overlay).padding(25).width(Length::Fill).into()
+    }
 
-    fn view(&self) -> iced_runtime::core::Element<'_, Self::Message, Self::Theme, Self::Renderer> {
-        // password form
-        let mut password =
-            iced_widget::text_input("Type password to unlock...", self.password.as_str())
-                .id(self.input_id.clone())
-                .secure(self.hide_input)
-                .width(Length::Fixed(300.0))
-                .padding(5)
-                .size(12.0)
-                .style(style::password());
-        if !self.auth_running() {
+    /// Password Input (Invisible when the Layout Shows Dots Instead)
+    ///
+    /// Only the output with keyboard focus edits the shared password.
+    fn password_input(&self, visible: bool) -> Element<'_, Message, Theme, Renderer> {
+        let theme = &self.theme;
+        let auth = self.auth();
+        let placeholder = auth.prompt().unwrap_or(&theme.placeholder);
+        let (width, opacity) = match visible {
+            true => (theme.input_width, self.opacity),
+            false => (0.0, 0.0),
+        };
+        let mut password = iced_widget::text_input(placeholder, auth.password())
+            .id(self.input_id.clone())
+            .secure(self.hide_input && !auth.echo())
+            .width(Length::Fixed(width))
+            .padding(5)
+            .size(theme.font_size)
+            .font(self.font)
+            .style(style::password(self.palette, auth.wrong(), opacity));
+        if self.active && !auth.is_waiting() {
             password = password
                 .on_input(Message::Typing)
                 .on_submit(Message::Submit);
         }
-        // build input controls
-        let size = 15.0;
-        let img = if self.hide_input {
-            self.hide_img.clone()
-        } else {
-            self.show_img.clone()
-        };
-        let show = iced_widget::Button::new(
-            iced_widget::Image::new(img)
-                .width(Length::Fixed(size))
-                .height(Length::Fixed(size)),
-        )
-        .on_press(Message::ToggleHide(None))
-        .style(style::show());
+        password.into()
+    }
 
-        let mut controls = Row::new().push(password);
-        let caps = if self.caps_lock {
-            let caps = iced_widget::Image::new(self.caps_img.clone())
-                .width(Length::Fixed(size))
-                .height(Length::Fixed(size));
-            iced_widget::Button::new(caps).style(style::show())
-        } else {
-            let empty = iced_widget::text("")
-                .width(Length::Fixed(size))
-                .height(Length::Fixed(size));
-            iced_widget::Button::new(empty).style(style::show())
-        };
-        controls = controls.push(caps).push(show);
+    /// Build Individual Layout Widget
+    fn widget(&self, widget: Widget) -> Element<'_, Message, Theme, Renderer> {
+        let theme = &self.theme;
+        let size = theme.icon_size;
+        let text =
+            |content: String, size: f32| iced_widget::text(content).size(size).font(self.font);
+        match widget {
+            Widget::Clock => {
+                let now = chrono::Local::now();
+                let size = theme.clock_size * self.layout.clock_scale;
+                text(now.format(&theme.clock_format).to_string(), size).into()
+            }
+            Widget::Date => {
+                let now = chrono::Local::now();
+                text(now.format(&theme.date_format).to_string(), theme.font_size).into()
+            }
+            Widget::User => text(self.realname.clone(), theme.font_size * 1.5).into(),
+            Widget::Avatar => match self.avatar.clone() {
+                Some(avatar) => iced_widget::Image::new(avatar)
+                    .width(Length::Fixed(theme.avatar_size))
+                    .height(Length::Fixed(theme.avatar_size))
+                    .into(),
+                None => iced_widget::text("").into(),
+            },
+            Widget::Password => self.password_input(true),
+            Widget::Dots => {
+                let auth = self.auth();
+                let count = auth.password().chars().count();
+                let dots = match count == 0 {
+                    true => auth.prompt().unwrap_or_default().to_owned(),
+                    false => "\u{25CF} ".repeat(count).trim_end().to_owned(),
+                };
+                text(dots, theme.font_size * 1.5).into()
+            }
+            Widget::Caps => {
+                let caps: Element<'_, Message, Theme, Renderer> = match self.caps_lock {
+                    true => iced_widget::Image::new(self.caps_img.clone())
+                        .width(Length::Fixed(size))
+                        .height(Length::Fixed(size))
+                        .into(),
+                    false => iced_widget::text("")
+                        .width(Length::Fixed(size))
+                        .height(Length::Fixed(size))
+                        .into(),
+                };
+                iced_widget::Button::new(caps)
+                    .style(style::show(self.palette, self.opacity))
+                    .into()
+            }
+            Widget::Toggle => {
+                let img = match self.hide_input {
+                    true => self.hide_img.clone(),
+                    false => self.show_img.clone(),
+                };
+                iced_widget::Button::new(
+                    iced_widget::Image::new(img)
+                        .width(Length::Fixed(size))
+                        .height(Length::Fixed(size)),
+                )
+                .on_press(Message::ToggleHide(None))
+                .style(style::show(self.palette, self.opacity))
+                .into()
+            }
+            Widget::Status => {
+                let lines = self.auth().messages();
+                lines
+                    .into_iter()
+                    .fold(Column::new(), |col, line| {
+                        col.push(text(line, theme.font_size))
+                    })
+                    .into()
+            }
+        }
+    }
 
-        // construct menu
-        let now = chrono::Local::now();
-        let message = iced_widget::text(now.format("%H:%M:%S")).size(32.0);
-        let menu = Column::new()
-            .push(message)
-            .push(controls)
-            .align_items(iced_wgpu::core::Alignment::Start);
-        let menu_box = container(menu).padding(10).style(style::menubox());
+    /// Lock Menu Composed from the Configured Layout
+    fn lock_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let theme = &self.theme;
+        let layout = &self.layout;
+        // compose menu from rows of widgets
+        let mut menu = Column::new();
+        for row in layout.rows.iter() {
+            let widgets = row.iter().map(|widget| self.widget(*widget));
+            menu = menu.push(Row::with_children(widgets));
+        }
+        // typing still requires a (hidden) password input
+        let has_input = layout.rows.iter().flatten().any(|w| *w == Widget::Password);
+        if !has_input {
+            menu = menu.push(self.password_input(false));
+        }
+        let menu = menu.align_items(match layout.align {
+            Align::Start => Alignment::Start,
+            Align::Center => Alignment::Center,
+            Align::End => Alignment::End,
+        });
+        let mut menu_box = container(menu).padding(theme.padding);
+        if layout.boxed {
+            let wrong = self.auth().wrong();
+            menu_box = menu_box.style(style::menubox(self.palette, wrong, self.opacity));
+        }
+        // position menu relative to its anchor
+        let (align_x, align_y) = match theme.anchor.unwrap_or(layout.anchor) {
+            Anchor::TopLeft => (Horizontal::Left, Vertical::Top),
+            Anchor::Top => (Horizontal::Center, Vertical::Top),
+            Anchor::TopRight => (Horizontal::Right, Vertical::Top),
+            Anchor::Left => (Horizontal::Left, Vertical::Center),
+            Anchor::Center => (Horizontal::Center, Vertical::Center),
+            Anchor::Right => (Horizontal::Right, Vertical::Center),
+            Anchor::BottomLeft => (Horizontal::Left, Vertical::Bottom),
+            Anchor::Bottom => (Horizontal::Center, Vertical::Bottom),
+            Anchor::BottomRight => (Horizontal::Right, Vertical::Bottom),
+        };
+        let [x, y] = theme.offset;
+        let x = x + self.shake_offset();
         container(menu_box)
-            .padding(25)
+            .padding(Padding {
+                top: (theme.margin + y).max(0.0),
+                right: (theme.margin - x).max(0.0),
+                bottom: (theme.margin - y).max(0.0),
+                left: (theme.margin + x).max(0.0),
+            })
             .width(Length::Fill)
             .height(Length::Fill)
-            .align_y(Vertical::Bottom)
+            .align_x(align_x)
+            .align_y(align_y)
             .into()
     }
+}
+
+impl iced_runtime::Program for UI {
+    type Theme = Theme;
+    type Message = Message;
+    type Renderer = iced_wgpu::Renderer;
+
+    fn view(&self) -> Element<'_, Self::Message, Self::Theme, Self::Renderer> {
+        let mut layout = Column::new().width(Length::Fill).height(Length::Fill);
+        if let Some(error) = self.shader_error.as_deref() {
+            layout = layout.push(self.error_view(error));
+        }
+        if self.lock {
+            layout = layout.push(self.lock_view());
+        }
+        layout.into()
+    }
     fn update(&mut self, message: Self::Message) -> iced_runtime::Command<Self::Message> {
         match message {
-            Message::Typing(e) => self.password = e,
-            Message::Submit => self.start_authenticate(),
+            Message::Typing(e) => self.auth().typing(e),
+            Message::Submit => self.auth().submit(),
             Message::Focus => return iced_widget::text_input::focus(self.input_id.clone()),
-            Message::Reset => self.password.clear(),
+            Message::Reset => self.auth().reset(),
             Message::CapsLock(caps) => self.caps_lock = caps,
             Message::ToggleHide(status) => self.hide_input = status.unwrap_or(!self.hide_input),
+            Message::Active(active) => self.active = active,
+            Message::Opacity(opacity) => self.opacity = opacity,
+            Message::ShaderError(error) => self.shader_error = error,
         }
         iced_runtime::Command::none()
     }
@@ -219,52 +401,112 @@ impl LastKeyTracker {
 /// Iced User Interface State Management and Operation
 pub struct IcedState {
     format: wgpu::TextureFormat,
-    renderer: Renderer,
     debug: Debug,
     state: Option<State<UI>>,
     viewport: Option<Viewport>,
     cursor: mouse::Cursor,
     clipboard: DummyClipboard,
     last_key: Option<LastKeyTracker>,
+    auth: Arc<Mutex<AuthController>>,
+    active: bool,
+    theme: ThemeConfig,
+    font: Font,
+    avatar: Option<iced_widget::image::Handle>,
+    lock: bool,
+    shader_error: Option<String>,
 }
 
 impl IcedState {
     pub fn new(
-        _adapter: &wgpu::Adapter,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
         format: wgpu::TextureFormat,
+        auth: Arc<Mutex<AuthController>>,
+        theme: ThemeConfig,
+        avatar: Option<&Path>,
+        lock: bool,
+        shader_error: Option<String>,
     ) -> Self {
         log::debug!("iced - building initial ui state");
+        // avatars are only loaded for layouts that display them
+        let show_avatar = layout(&theme)
+            .rows
+            .iter()
+            .flatten()
+            .any(|w| *w == Widget::Avatar);
+        let avatar = avatar.filter(|_| show_avatar).and_then(|path| {
+            let size = theme.avatar_size.ceil() as u32;
+            match user::avatar(path, size) {
+                Ok(img) => Some(iced_widget::image::Handle::from_pixels(
+                    img.width(),
+                    img.height(),
+                    img.into_raw(),
+                )),
+                Err(err) => {
+                    log::error!("iced - failed to load avatar: {err:#}");
+                    None
+                }
+            }
+        });
         let debug = Debug::default();
-        let engine = Backend::new(&device, &queue, Settings::default(), format);
-        let renderer = Renderer::new(engine, Font::default(), Pixels::from(32));
         Self {
             format,
             debug,
-            renderer,
             viewport: None,
             state: None,
             cursor: mouse::Cursor::Available(Point::new(0.0, 0.0)),
             clipboard: DummyClipboard {},
             last_key: None,
+            auth,
+            active: false,
+            font: style::font(&theme),
+            theme,
+            avatar,
+            lock,
+            shader_error,
         }
     }
 
     /// Configure State for Given Viewport Size
-    pub fn configure(&mut self, width: u32, height: u32) {
+    pub fn configure(&mut self, width: u32, height: u32, renderer: &mut Renderer) {
         log::debug!("iced - configuing viewports from surface ({width}/{height})");
-        let ui = UI::new();
+        let ui = UI::new(
+            Arc::clone(&self.auth),
+            self.active,
+            self.theme.clone(),
+            self.font,
+            self.avatar.clone(),
+            self.lock,
+            self.shader_error.clone(),
+        );
         let bounds = Size::new(width, height);
         let viewport = Viewport::with_physical_size(bounds, 1.0);
         let size = viewport.logical_size();
         self.viewport = Some(viewport);
-        self.state = Some(State::new(ui, size, &mut self.renderer, &mut self.debug));
+        self.state = Some(State::new(ui, size, renderer, &mut self.debug));
+    }
+
+    /// Display or Clear Shader Compiler Diagnostics
+    pub fn set_shader_error(&mut self, error: Option<String>) {
+        self.shader_error = error.clone();
+        if let Some(state) = self.state.as_mut() {
+            state.queue_message(Message::ShaderError(error));
+        }
+    }
+
+    /// Allow or Prevent the UI from Editing the Shared Password
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+        if let Some(state) = self.state.as_mut() {
+            state.queue_message(Message::Active(active));
+        }
     }
 
     /// Supply Keyboard Events to UI
+    ///
+    /// Input arriving before the surface is configured is dropped.
     pub fn key_event(&mut self, event: keyboard::Event) {
-        let state = self.state.as_mut().expect("ui state not configured yet");
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
         match &event {
             keyboard::Event::KeyPressed { key, .. } => match key.clone() {
                 TAB => state.queue_message(Message::Focus),
@@ -288,7 +530,9 @@ impl IcedState {
 
     /// Supply Mouse Events to UI
     pub fn mouse_event(&mut self, event: mouse::Event) {
-        let state = self.state.as_mut().expect("ui state not configured yet");
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
         state.queue_event(Event::Mouse(event));
         match event {
             mouse::Event::CursorMoved { position } => {
@@ -298,14 +542,13 @@ impl IcedState {
         }
     }
 
-    /// Check if UI State if Authenticated
+    /// Collect Lock State Exposed to Shaders
     #[inline]
-    pub fn is_authenticated(&self) -> bool {
+    pub fn status(&self) -> LockStatus {
         self.state
             .as_ref()
-            .expect("ui state not configured yet")
-            .program()
-            .is_authenticated()
+            .map(|state| state.program().status())
+            .unwrap_or_default()
     }
 
     /// Render UI Frame using WGPU
@@ -313,15 +556,19 @@ impl IcedState {
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        renderer: &mut Renderer,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
+        opacity: f32,
     ) {
         // update rendering with contents
-        let state = self.state.as_mut().unwrap();
-        let viewport = self.viewport.as_ref().unwrap();
+        let (Some(state), Some(viewport)) = (self.state.as_mut(), self.viewport.as_ref()) else {
+            return;
+        };
         let bounds = viewport.logical_size();
         // spam focus on password field
         state.queue_message(Message::Focus);
+        state.queue_message(Message::Opacity(opacity));
         // handle backspace repitition
         if let Some(last_key) = self.last_key.as_ref() {
             if last_key.should_repeat() {
@@ -332,10 +579,13 @@ impl IcedState {
         let (_, command) = state.update(
             bounds,
             self.cursor,
-            &mut self.renderer,
+            renderer,
             &Theme::Dark,
             &renderer::Style {
-                text_color: Color::WHITE,
+                text_color: Color {
+                    a: opacity,
+                    ..Color::WHITE
+                },
             },
             &mut self.clipboard,
             &mut self.debug,
@@ -353,14 +603,9 @@ impl IcedState {
             }
         }
         // complete operations (if any)
-        state.operate(
-            &mut self.renderer,
-            operations.into_iter(),
-            bounds,
-            &mut self.debug,
-        );
+        state.operate(renderer, operations.into_iter(), bounds, &mut self.debug);
         // complete rendering
-        self.renderer.with_primitives(|backend, primitive| {
+        renderer.with_primitives(|backend, primitive| {
             backend.present(
                 &device,
                 &queue,
@@ -0,0 +1,210 @@
+//! Idle Daemon Launching the Screensaver and Lockscreen after Inactivity
+use std::process::{Child, Command};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay_client_toolkit::reexports::calloop::EventLoop;
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
+use smithay_client_toolkit::reexports::protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
+
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+
+use dynlock_lib::{Cli, IdleConfig};
+
+/// Interval between Checks for an Exited Screensaver or Lock Process
+const REAP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Action Started once its Idle Timeout Elapses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Screensaver,
+    Lock,
+}
+
+/// Internal Idle Daemon State
+struct IdleData {
+    cli: Cli,
+    grace: Option<Duration>,
+    child: Option<Child>,
+}
+
+/// Check if the Child Process is Still Running (Reaping it Otherwise)
+fn running(child: &mut Option<Child>) -> bool {
+    let Some(process) = child.as_mut() else {
+        return false;
+    };
+    match process.try_wait() {
+        Ok(None) => true,
+        Ok(Some(status)) => {
+            log::info!("idle - dynlock (pid={}) exited: {status}", process.id());
+            child.take();
+            false
+        }
+        Err(err) => {
+            log::error!(
+                "idle - failed to check dynlock (pid={}): {err}",
+                process.id()
+            );
+            child.take();
+            false
+        }
+    }
+}
+
+impl IdleData {
+    /// Spawn Dynlock in Screensaver or Lockscreen Mode
+    ///
+    /// When a lock timeout follows, the screensaver is started as a lock
+    /// with a grace period so it escalates in place rather than being
+    /// replaced by a second session-lock client.
+    fn spawn(&self, stage: Stage) -> Result<Child> {
+        let exe = std::env::current_exe().context("failed to locate dynlock executable")?;
+        let mut command = Command::new(exe);
+        match (stage, self.grace) {
+            (Stage::Screensaver, Some(grace)) => {
+                let grace = grace.as_secs_f32().to_string();
+                command.args(["--screensave", "false", "--grace", &grace]);
+            }
+            (Stage::Screensaver, None) => {
+                command.args(["--screensave", "true"]);
+            }
+            (Stage::Lock, _) => {
+                command.args(["--screensave", "false"]);
+            }
+        }
+        let flags = [
+            ("--config", &self.cli.config),
+            ("--shader", &self.cli.shader),
+            ("--background", &self.cli.background),
+        ];
+        for (flag, value) in flags {
+            if let Some(value) = value {
+                command.args([flag, value]);
+            }
+        }
+        let child = command.spawn().context("failed to spawn dynlock")?;
+        log::info!("idle - started {stage:?} (pid={})", child.id());
+        Ok(child)
+    }
+    /// Reap Exited Process so the Timeouts Re-Arm
+    fn reap(&mut self) {
+        running(&mut self.child);
+    }
+    /// Start the Stage whose Idle Timeout has Elapsed
+    ///
+    /// A running screensaver is never killed since it holds the session
+    /// lock, it either escalates itself or exits once dismissed.
+    fn idled(&mut self, stage: Stage) {
+        self.reap();
+        if self.child.is_some() {
+            log::debug!("idle - {stage:?} timeout ignored while dynlock is running");
+            return;
+        }
+        match self.spawn(stage) {
+            Ok(child) => self.child = Some(child),
+            Err(err) => log::error!("idle - {err:#}"),
+        }
+    }
+}
+
+/// Run Idle Daemon with Timeouts from the Configuration
+pub fn idle(cli: Cli, config: IdleConfig) -> Result<()> {
+    let stages: Vec<(Stage, Duration)> = [
+        (Stage::Screensaver, config.screensaver()),
+        (Stage::Lock, config.lock()),
+    ]
+    .into_iter()
+    .filter_map(|(stage, timeout)| timeout.map(|t| (stage, t)))
+    .collect();
+    if stages.is_empty() {
+        return Err(anyhow!("no idle timeouts are configured"));
+    }
+    // the screensaver escalates itself once the lock timeout is reached
+    let grace = config
+        .lock()
+        .zip(config.screensaver())
+        .and_then(|(lock, screensaver)| lock.checked_sub(screensaver))
+        .filter(|grace| !grace.is_zero());
+
+    let conn =
+        Connection::connect_to_env().context("wayland - failed to open wayland connection")?;
+    let (globals, event_queue) =
+        registry_queue_init(&conn).context("wayland - failed to register event-queue")?;
+    let qh: QueueHandle<IdleData> = event_queue.handle();
+
+    // idle notifications (unlike input notifications) honour idle inhibitors
+    let notifier: ExtIdleNotifierV1 = globals
+        .bind(&qh, 1..=1, ())
+        .context("wayland - ext-idle-notify not supported")?;
+    let seat: wl_seat::WlSeat = globals
+        .bind(&qh, 1..=1, ())
+        .context("wayland - no seat available")?;
+    for (stage, timeout) in stages.iter() {
+        log::info!("idle - starting {stage:?} after {timeout:?} of inactivity");
+        let millis = timeout.as_millis().min(u32::MAX as u128) as u32;
+        notifier.get_idle_notification(millis, &seat, &qh, *stage);
+    }
+
+    let mut event_loop: EventLoop<IdleData> =
+        EventLoop::try_new().context("wayland - failed to init event-loop")?;
+    WaylandSource::new(conn, event_queue)
+        .insert(event_loop.handle())
+        .map_err(|err| anyhow!("wayland - failed to insert event source: {err}"))?;
+    event_loop
+        .handle()
+        .insert_source(Timer::from_duration(REAP_INTERVAL), |_, _, data| {
+            data.reap();
+            TimeoutAction::ToDuration(REAP_INTERVAL)
+        })
+        .expect("failed to schedule process reaper");
+
+    let mut data = IdleData {
+        cli,
+        grace,
+        child: None,
+    };
+    event_loop
+        .run(None, &mut data, |_| {})
+        .context("event loop crashed")
+}
+
+impl Dispatch<ExtIdleNotificationV1, Stage> for IdleData {
+    fn event(
+        state: &mut Self,
+        _: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        stage: &Stage,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_idle_notification_v1::Event::Idled => state.idled(*stage),
+            ext_idle_notification_v1::Event::Resumed => {
+                log::debug!("idle - activity resumed before {stage:?} timeout")
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for IdleData {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+wayland_client::delegate_noop!(IdleData: ExtIdleNotifierV1);
+wayland_client::delegate_noop!(IdleData: ignore wl_seat::WlSeat);
@@ -1,12 +1,12 @@
 //! Smithay Wayland LockScreen Generation and Runtime
-use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{anyhow, Context, Result};
 
 use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
-use smithay_client_toolkit::reexports::calloop::EventLoop;
+use smithay_client_toolkit::reexports::calloop::{EventLoop, LoopHandle};
 use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
 
 use smithay_client_toolkit::compositor::{CompositorHandler, CompositorState};
@@ -14,7 +14,7 @@ use smithay_client_toolkit::output::{OutputHandler, OutputState};
 use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
 use smithay_client_toolkit::registry_handlers;
 use smithay_client_toolkit::seat::keyboard::{KeyEvent, KeyboardHandler, Keysym, Modifiers};
-use smithay_client_toolkit::seat::pointer::{PointerEvent, PointerHandler};
+use smithay_client_toolkit::seat::pointer::{PointerEvent, PointerEventKind, PointerHandler};
 use smithay_client_toolkit::seat::Capability;
 use smithay_client_toolkit::seat::{SeatHandler, SeatState};
 use smithay_client_toolkit::session_lock::{SessionLock, SessionLockHandler, SessionLockState};
@@ -27,13 +27,27 @@ use wayland_client::protocol::{
 };
 use wayland_client::{Connection, Proxy, QueueHandle};
 
+use crate::auth::AuthController;
 use crate::event::{keypress_event, modifiers_event, mouse_event};
-use crate::graphics::{Background, State};
-use dynlock_lib::Settings;
+use crate::graphics::{Backgrounds, GraphicsContext, ShaderPack, SoftwareState, State};
+use crate::playlist::Playlist;
+use dynlock_lib::{RendererBackend, Settings};
+
+/// Maximum Wait between Event-Loop Iterations
+const EVENT_LOOP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Interval between Checks for Shader Modifications in Watch Mode
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Map of Wayland Surface Ids to Wgpu Renderering Instances
 type RenderersMap = BTreeMap<u32, State<'static>>;
 
+/// Map of Wayland Surface Ids to CPU Renderers used when Wgpu Fails
+type SoftwareMap = BTreeMap<u32, SoftwareState>;
+
+/// Map of Wayland Output Ids to their Assigned Lock Surfaces
+type LockSurfacesMap = BTreeMap<u32, SessionLockSurface>;
+
 /// Wayland Seat Objects Tracker
 struct SeatObject {
     seat: wl_seat::WlSeat,
@@ -48,6 +62,7 @@ struct AppData {
     settings: Settings,
     // common compositer components
     conn: Connection,
+    loop_handle: LoopHandle<'static, AppData>,
     compositor_state: CompositorState,
     output_state: OutputState,
     registry_state: RegistryState,
@@ -55,10 +70,22 @@ struct AppData {
     // lockscreen components
     session_lock_state: SessionLockState,
     session_lock: Option<SessionLock>,
-    lock_surfaces: Vec<SessionLockSurface>,
+    lock_surfaces: LockSurfacesMap,
     // rendering components
     renderers: Arc<RwLock<RenderersMap>>,
-    background: Background,
+    software: SoftwareMap,
+    software_only: bool,
+    frame_interval: Option<Duration>,
+    unlock_start: Option<Instant>,
+    graphics: Option<Arc<GraphicsContext>>,
+    backgrounds: Backgrounds,
+    shader_modified: Option<SystemTime>,
+    playlist: Playlist,
+    pinned: BTreeSet<u32>,
+    // authentication shared by every output
+    auth: Arc<Mutex<AuthController>>,
+    focused: Option<u32>,
+    active: Option<u32>,
     // input components
     seat_state: SeatState,
     seat_objects: Vec<SeatObject>,
@@ -72,8 +99,11 @@ impl AppData {
     fn modify(&mut self, key: u32, f: impl FnOnce(&mut State<'static>)) {
         let arc = Arc::clone(&self.renderers);
         let mut renderers = arc.write().expect("renderers modify failed");
-        let renderer = renderers.get_mut(&key).expect("invalid renderer");
-        f(renderer)
+        match renderers.get_mut(&key) {
+            Some(renderer) => f(renderer),
+            None if self.software.contains_key(&key) => {}
+            None => log::warn!("wayland - no renderer for surface (surface={key})"),
+        }
     }
     // Function Wrapper to Run Against All Renderer Objects
     fn modify_all(&mut self, f: impl Fn(&mut State<'static>)) {
@@ -83,35 +113,400 @@ impl AppData {
             f(renderer);
         }
     }
+    /// Let Only the Focused Output (or the First) Edit the Shared Password
+    fn focus(&mut self) {
+        let arc = Arc::clone(&self.renderers);
+        let mut renderers = arc.write().expect("renderers focus failed");
+        let exists = |key: &u32| renderers.contains_key(key) || self.software.contains_key(key);
+        let first = renderers.keys().chain(self.software.keys()).min().copied();
+        self.active = self.focused.filter(exists).or(first);
+        for (key, renderer) in renderers.iter_mut() {
+            renderer.set_active(Some(*key) == self.active);
+        }
+    }
+    /// Edit the Shared Password Directly for Outputs Drawn in Software
+    fn edit_password(&mut self, event: &KeyEvent) {
+        let mut auth = self.auth.lock().expect("auth controller lock failed");
+        if auth.is_waiting() {
+            return;
+        }
+        let mut password = auth.password().to_owned();
+        match event.keysym {
+            Keysym::Return | Keysym::KP_Enter => return auth.submit(),
+            Keysym::Escape => return auth.reset(),
+            Keysym::BackSpace => {
+                password.pop();
+            }
+            _ => match event.utf8.as_deref() {
+                Some(text) if !text.chars().any(char::is_control) => password.push_str(text),
+                _ => return,
+            },
+        }
+        auth.typing(password);
+    }
+    /// Apply Authentication Progress and Unlock once Successful
+    fn update_auth(&mut self) {
+        let authenticated = {
+            let mut auth = self.auth.lock().expect("auth controller lock failed");
+            auth.poll();
+            auth.is_authenticated()
+        };
+        if authenticated {
+            self.begin_unlock();
+        }
+        // unlock only once the fade-out has been rendered
+        if let Some(start) = self.unlock_start {
+            if start.elapsed() >= self.settings.animation.fade_out() {
+                self.exit = true;
+            }
+        }
+    }
+    /// Redraw Outputs Rendered in Software whose State has Changed
+    fn render_software(&mut self) {
+        for renderer in self.software.values_mut() {
+            renderer.render();
+        }
+    }
+    /// Render Surface and Request Callback for the Next Frame
+    ///
+    /// Skipped when a frame is already scheduled for the surface.
+    fn render_surface(&mut self, qh: &QueueHandle<Self>, surface: &wl_surface::WlSurface) {
+        let key = surface.id().protocol_id();
+        self.update_auth();
+        let mut failing = false;
+        self.modify(key, |r| {
+            if r.frame_scheduled() {
+                return;
+            }
+            surface.frame(qh, surface.clone());
+            r.schedule_frame();
+            // commit regardless so the frame callback is still delivered
+            if !r.render() {
+                surface.commit();
+            }
+            failing = r.is_failing();
+        });
+        if failing {
+            self.degrade(key);
+        }
+    }
+    /// Recompile Shader on All Outputs when its Files have been Modified
+    ///
+    /// Compile errors are shown inline while the last good pack keeps running.
+    fn reload_shader(&mut self) {
+        let path = self.settings.shader_path.clone();
+        let modified = match ShaderPack::modified(&path) {
+            Ok(modified) => modified,
+            Err(err) => {
+                log::warn!("watch - failed to stat shader {path:?}: {err}");
+                return;
+            }
+        };
+        if self.shader_modified.is_some_and(|last| last == modified) {
+            return;
+        }
+        let Some(graphics) = self.graphics.as_ref().map(Arc::clone) else {
+            return;
+        };
+        self.shader_modified = Some(modified);
+        log::info!("watch - reloading shader {path:?}");
+        match graphics.reload(&path) {
+            Ok(pack) => self.modify_all(|r| {
+                r.set_pack(Arc::clone(&pack));
+                r.set_shader_error(None);
+            }),
+            Err(err) => {
+                log::error!("watch - failed to compile shader: {err:#}");
+                let error = format!("{err:#}");
+                self.modify_all(|r| r.set_shader_error(Some(error.clone())));
+            }
+        }
+    }
+    /// Advance the Playlist and Crossfade Outputs into the Next Entries
+    ///
+    /// Outputs with a pinned shader only rotate their background.
+    fn rotate(&mut self) {
+        if self.unlock_start.is_some() {
+            return;
+        }
+        let Some(graphics) = self.graphics.as_ref().map(Arc::clone) else {
+            return;
+        };
+        // swap background images before rendering the next shader over them
+        let mut background = None;
+        if self.playlist.rotates_backgrounds() {
+            if let Some(path) = self.playlist.next_background() {
+                log::info!("playlist - rotating background {path:?}");
+                match Backgrounds::load(&path) {
+                    Ok(backgrounds) => {
+                        self.backgrounds = backgrounds;
+                        graphics.clear_backgrounds();
+                        let key = self.backgrounds.key(None);
+                        let view = graphics.background(&key, || self.backgrounds.get(None));
+                        background = Some(Arc::new(view));
+                    }
+                    Err(err) => log::error!("playlist - failed to load background: {err:#}"),
+                }
+            }
+        }
+        let mut pack = None;
+        if let Some(path) = self.playlist.next_shader() {
+            log::info!("playlist - rotating shader {path:?}");
+            match graphics.load(&path) {
+                Ok(compiled) => {
+                    graphics.set_pack(Arc::clone(&compiled));
+                    pack = Some(compiled);
+                }
+                Err(err) => log::error!("playlist - failed to compile shader: {err:#}"),
+            }
+        }
+        if pack.is_none() && background.is_none() {
+            return;
+        }
+        let duration = self.settings.playlist.crossfade();
+        let arc = Arc::clone(&self.renderers);
+        let mut renderers = arc.write().expect("renderers rotate failed");
+        for (key, renderer) in renderers.iter_mut() {
+            let pack = match self.pinned.contains(key) {
+                true => None,
+                false => pack.clone(),
+            };
+            if pack.is_some() || background.is_some() {
+                renderer.crossfade(pack, background.clone(), duration);
+            }
+        }
+    }
+    /// Escalate the Screensaver into a Password Protected Lock in Place
+    ///
+    /// The existing session lock and renderers are kept so the
+    /// session never becomes unlocked in between.
+    fn escalate(&mut self) {
+        if self.settings.lock || self.unlock_start.is_some() {
+            return;
+        }
+        log::info!("grace period expired, password required to unlock");
+        self.settings.lock = true;
+        let arc = Arc::clone(&self.renderers);
+        let mut renderers = arc.write().expect("renderers escalate failed");
+        for renderer in renderers.values_mut() {
+            renderer.lock(Arc::clone(&self.auth), &self.settings);
+        }
+        drop(renderers);
+        for renderer in self.software.values_mut() {
+            renderer.lock();
+        }
+        self.focus();
+    }
+    /// Start Fade-Out Animation on All Outputs before Unlocking
+    fn begin_unlock(&mut self) {
+        if self.unlock_start.is_some() {
+            return;
+        }
+        log::debug!("wayland - fading out before unlock");
+        self.unlock_start = Some(Instant::now());
+        self.modify_all(|r| r.fade_out());
+    }
+    /// Generate Lock Surface and Renderer for the Specified Output
+    ///
+    /// Outputs stay locked even when no renderer can be created for them.
+    fn lock_output(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        session_lock: &SessionLock,
+        output: &wl_output::WlOutput,
+    ) {
+        // generate wayland surfaces
+        let surface = self.compositor_state.create_surface(qh);
+        let lock_surface = session_lock.create_lock_surface(surface, output, qh);
+        let oid = output.id().protocol_id();
+        if !self.software_only {
+            match self.spawn_renderer(conn, &lock_surface, output) {
+                Ok(()) => {
+                    self.lock_surfaces.insert(oid, lock_surface);
+                    self.focus();
+                    return;
+                }
+                Err(err) => log::error!("wgpu - renderer failed (output={oid}): {err:#}"),
+            }
+        }
+        log::warn!("wayland - using software rendering (output={oid})");
+        let name = self.output_state.info(output).and_then(|info| info.name);
+        self.spawn_software(&lock_surface, name.as_deref(), None);
+        self.lock_surfaces.insert(oid, lock_surface);
+        self.focus();
+    }
+    /// Build Wgpu Renderer for the Lock Surface of the Specified Output
+    fn spawn_renderer(
+        &mut self,
+        conn: &Connection,
+        lock_surface: &SessionLockSurface,
+        output: &wl_output::WlOutput,
+    ) -> Result<()> {
+        // adopt surface using shared graphics context (built on first use)
+        let surface = match self.graphics.as_ref() {
+            Some(graphics) => graphics.create_surface(conn, lock_surface)?,
+            None => {
+                // skip wgpu on later outputs once the context cannot be built
+                let context =
+                    pollster::block_on(GraphicsContext::new(conn, &self.settings, lock_surface));
+                self.software_only = context.is_err();
+                let (graphics, surface) = context?;
+                self.graphics = Some(Arc::new(graphics));
+                surface
+            }
+        };
+        let graphics = Arc::clone(self.graphics.as_ref().expect("missing graphics context"));
+        // generate wgpu renderer for surface
+        let key = lock_surface.wl_surface().id().protocol_id();
+        let name = self.output_state.info(output).and_then(|info| info.name);
+        let background = graphics.background(&self.backgrounds.key(name.as_deref()), || {
+            self.backgrounds.get(name.as_deref())
+        });
+        // outputs may be pinned to a specific shader in the playlist
+        let pinned = name
+            .as_deref()
+            .and_then(|name| self.playlist.output_shader(name));
+        let pack = match pinned {
+            Some(path) => match graphics.load(&path) {
+                Ok(pack) => {
+                    log::info!("playlist - pinned shader {path:?} to output {name:?}");
+                    self.pinned.insert(key);
+                    pack
+                }
+                Err(err) => {
+                    log::error!("playlist - failed to compile pinned shader: {err:#}");
+                    graphics.pack()
+                }
+            },
+            None => graphics.pack(),
+        };
+        let auth = Arc::clone(&self.auth);
+        let mut renderer = State::new(graphics, surface, pack, background, auth, &self.settings);
+        if self.unlock_start.is_some() {
+            renderer.fade_out();
+        }
+        let oid = output.id().protocol_id();
+        log::debug!("wayland - renderer assigned (output={oid}, surface={key})");
+        // track wl-surface to rendering pipeline
+        let arc = Arc::clone(&self.renderers);
+        let mut renderers = arc.write().expect("renderer write lock failed");
+        renderers.insert(key, renderer);
+        Ok(())
+    }
+    /// Build CPU Renderer for the Lock Surface (Sized if Already Configured)
+    fn spawn_software(
+        &mut self,
+        lock_surface: &SessionLockSurface,
+        name: Option<&str>,
+        size: Option<(u32, u32)>,
+    ) {
+        let surface = lock_surface.wl_surface().clone();
+        let key = surface.id().protocol_id();
+        let auth = Arc::clone(&self.auth);
+        let background = self.backgrounds.get(name);
+        match SoftwareState::new(&self.shm, surface, auth, background, &self.settings) {
+            Ok(mut renderer) => {
+                renderer.set_caps_lock(self.modifiers.is_some_and(|m| m.caps_lock));
+                if let Some((width, height)) = size {
+                    renderer.configure(width, height);
+                    renderer.render();
+                }
+                log::debug!("wayland - software renderer assigned (surface={key})");
+                self.software.insert(key, renderer);
+            }
+            Err(err) => log::error!("shm - renderer failed, output stays blank: {err:#}"),
+        }
+    }
+    /// Replace a Failing Wgpu Renderer with a Software Renderer
+    fn degrade(&mut self, key: u32) {
+        let arc = Arc::clone(&self.renderers);
+        let mut renderers = arc.write().expect("renderer write lock failed");
+        let Some(renderer) = renderers.remove(&key) else {
+            return;
+        };
+        drop(renderers);
+        log::error!("wgpu - renderer keeps failing, falling back to software (surface={key})");
+        // outputs added later must not reuse a lost device
+        if self
+            .graphics
+            .as_ref()
+            .is_some_and(|graphics| graphics.is_lost())
+        {
+            self.software_only = true;
+        }
+        let size = renderer.size();
+        drop(renderer);
+        let found = (self.lock_surfaces.iter())
+            .find(|(_, s)| s.wl_surface().id().protocol_id() == key)
+            .map(|(oid, s)| (*oid, s.clone()));
+        if let Some((oid, lock_surface)) = found {
+            let name = (self.output_state.outputs())
+                .find(|output| output.id().protocol_id() == oid)
+                .and_then(|output| self.output_state.info(&output))
+                .and_then(|info| info.name);
+            self.spawn_software(&lock_surface, name.as_deref(), Some(size));
+        }
+        self.focus();
+    }
+    /// Teardown Lock Surface and Renderer for the Specified Output
+    fn unlock_output(&mut self, output: &wl_output::WlOutput) {
+        let oid = output.id().protocol_id();
+        let Some(lock_surface) = self.lock_surfaces.remove(&oid) else {
+            return;
+        };
+        // renderer must be dropped before its underlying wl-surface
+        let key = lock_surface.wl_surface().id().protocol_id();
+        let arc = Arc::clone(&self.renderers);
+        let mut renderers = arc.write().expect("renderer write lock failed");
+        renderers.remove(&key);
+        drop(renderers);
+        self.software.remove(&key);
+        self.pinned.remove(&key);
+        self.focus();
+        log::debug!("wayland - renderer removed (output={oid}, surface={key})");
+    }
 }
 
 /// Run LockScren with Configured Settings
-pub fn lock(settings: Settings) -> Result<()> {
+pub fn lock(settings: Settings, playlist: Playlist) -> Result<()> {
     let conn =
         Connection::connect_to_env().context("wayland - failed to open wayland connection")?;
     let (globals, event_queue) =
         registry_queue_init(&conn).context("wayland - failed to register event-queue")?;
     let qh: QueueHandle<AppData> = event_queue.handle();
 
-    // take screenshots of outputs
-    let background = match &settings.background {
-        Some(path) => {
-            let img = std::fs::read(path).context("failed to read background image")?;
-            image::load_from_memory(&img)
-                .context("invalid background image")?
-                .to_rgba8()
-        }
-        None => {
-            // take screenshot of current output (TODO: multimonitor support)
-            let wayshot = libwayshot::WayshotConnection::from_connection(conn.clone())
-                .context("wayshot - screenshot connection failed")?;
-            wayshot.screenshot_all(false).context("screenshot failed")?
-        }
+    // take screenshots of outputs (failures must never prevent locking)
+    let backgrounds = match &settings.background {
+        Some(path) => Backgrounds::load(path).context("failed to load background image"),
+        None => Backgrounds::screenshot(&conn),
     };
+    let backgrounds = backgrounds.unwrap_or_else(|err| {
+        log::error!("falling back to a solid background: {err:#}");
+        Backgrounds::solid(image::Rgba([0, 0, 0, 255]))
+    });
+
+    // locks with a grace period begin in screensaver mode
+    let mut settings = settings;
+    if settings.grace.is_some() {
+        settings.lock = false;
+    }
 
     // prepare event-loop
     let mut event_loop: EventLoop<AppData> =
         EventLoop::try_new().context("wayland - failed to init event-loop")?;
+    let frame_interval = settings
+        .max_fps
+        .filter(|fps| *fps > 0)
+        .map(|fps| Duration::from_secs_f64(1.0 / fps as f64));
+
+    let software_only = settings.renderer.backend == RendererBackend::Software;
+    if software_only {
+        log::info!("software rendering enabled, shaders are disabled");
+    }
+
+    // a single controller handles every authentication attempt
+    let auth = Arc::new(Mutex::new(AuthController::new(&settings.auth)?));
 
     let mut app_data = AppData {
         exit: false,
@@ -119,6 +514,7 @@ pub fn lock(settings: Settings) -> Result<()> {
         settings,
         // compositor components
         conn: conn.clone(),
+        loop_handle: event_loop.handle(),
         compositor_state: CompositorState::bind(&globals, &qh).unwrap(),
         output_state: OutputState::new(&globals, &qh),
         registry_state: RegistryState::new(&globals),
@@ -126,10 +522,22 @@ pub fn lock(settings: Settings) -> Result<()> {
         // lockscreen components
         session_lock_state: SessionLockState::new(&globals, &qh),
         session_lock: None,
-        lock_surfaces: Vec::new(),
+        lock_surfaces: LockSurfacesMap::new(),
         // rendering components
         renderers: Arc::new(RwLock::new(RenderersMap::new())),
-        background,
+        software: SoftwareMap::new(),
+        software_only,
+        frame_interval,
+        unlock_start: None,
+        graphics: None,
+        backgrounds,
+        shader_modified: None,
+        playlist,
+        pinned: BTreeSet::new(),
+        // authentication components
+        auth,
+        focused: None,
+        active: None,
         // input management components
         seat_state: SeatState::new(&globals, &qh),
         seat_objects: vec![],
@@ -138,6 +546,48 @@ pub fn lock(settings: Settings) -> Result<()> {
         pointer: None,
     };
 
+    // poll shader for modifications when hot-reloading
+    if app_data.settings.watch {
+        let path = &app_data.settings.shader_path;
+        log::info!("watching shader for changes: {path:?}");
+        app_data.shader_modified = ShaderPack::modified(path).ok();
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(WATCH_INTERVAL), |_, _, app_data| {
+                app_data.reload_shader();
+                TimeoutAction::ToDuration(WATCH_INTERVAL)
+            })
+            .expect("failed to schedule shader watcher");
+    }
+
+    // rotate playlist entries while locked (the watched shader stays put)
+    if let Some(interval) = app_data.settings.playlist.interval() {
+        match app_data.settings.watch {
+            true => log::warn!("playlist rotation is disabled while watching a shader"),
+            false => {
+                log::info!("rotating playlist every {interval:?}");
+                event_loop
+                    .handle()
+                    .insert_source(Timer::from_duration(interval), move |_, _, app_data| {
+                        app_data.rotate();
+                        TimeoutAction::ToDuration(interval)
+                    })
+                    .expect("failed to schedule playlist rotation");
+            }
+        }
+    }
+
+    // require a password once the grace period has expired
+    if let Some(grace) = app_data.settings.grace {
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(grace), |_, _, app_data| {
+                app_data.escalate();
+                TimeoutAction::Drop
+            })
+            .expect("failed to schedule lock escalation");
+    }
+
     app_data.session_lock = Some(
         app_data
             .session_lock_state
@@ -149,44 +599,27 @@ pub fn lock(settings: Settings) -> Result<()> {
         .insert(event_loop.handle())
         .unwrap();
 
-    //TODO: need some sort of leaky-bucket model here to track fps and
-    //allow for shorter waits when frames begin to slow
-    let fps = 60;
-    let dist = 1000 / fps;
-    let handle = event_loop.handle();
-    handle
-        .insert_source(
-            Timer::from_duration(Duration::from_millis(dist)),
-            move |_, _, app_data| {
-                let arc = Arc::clone(&app_data.renderers);
-                let mut renderers = arc.write().expect("renderer write lock failed");
-                for renderer in renderers.values_mut() {
-                    renderer.render();
-                    if renderer.is_authenticated() {
-                        app_data.exit = true
-                    }
-                }
-                log::debug!("frame rendered!");
-                TimeoutAction::ToDuration(Duration::from_millis(dist))
-            },
-        )
-        .expect("failed to schedule rendering loop");
-
     let start = SystemTime::now();
     let signal = event_loop.get_signal();
     event_loop
-        .run(
-            std::time::Duration::from_millis(dist * 2),
-            &mut app_data,
-            |app_data| {
-                // handle exit when specified
-                if app_data.exit {
-                    app_data.session_lock.take().unwrap().unlock();
-                    app_data.conn.roundtrip().unwrap();
-                    signal.stop();
+        .run(EVENT_LOOP_TIMEOUT, &mut app_data, |app_data| {
+            // software outputs have no frame callbacks driving them
+            if !app_data.software.is_empty() {
+                app_data.update_auth();
+                app_data.render_software();
+            }
+            // handle exit when specified
+            if app_data.exit {
+                let session_lock = app_data.session_lock.take().unwrap();
+                // only a successful unlock may end the lock, anything else stays locked
+                match app_data.unlock_start.is_some() {
+                    true => session_lock.unlock(),
+                    false => log::error!("wayland - exiting without unlocking the session"),
                 }
-            },
-        )
+                app_data.conn.roundtrip().unwrap();
+                signal.stop();
+            }
+        })
         .context("event loop crashed")?;
 
     let seconds = SystemTime::now()
@@ -204,36 +637,8 @@ impl SessionLockHandler for AppData {
     fn locked(&mut self, conn: &Connection, qh: &QueueHandle<Self>, session_lock: SessionLock) {
         // prepare sufaces and renderers for lockscreen
         log::debug!("wayland - locking screen and generating renderers");
-        let arc = Arc::clone(&self.renderers);
-        let mut renderers = arc.write().expect("renderer write lock failed");
         for output in self.output_state.outputs() {
-            // generate wayland surfaces
-            let surface = self.compositor_state.create_surface(&qh);
-            let lock_surface = session_lock.create_lock_surface(surface, &output, qh);
-            // generate wgpu renderer for surface
-            let key = lock_surface.wl_surface().id().protocol_id();
-            let renderer = pollster::block_on(State::new(
-                conn,
-                self.background.clone(),
-                &self.settings.shader,
-                self.settings.lock,
-                &lock_surface,
-            ));
-            match renderer {
-                Ok(renderer) => {
-                    // track outputs to wl-surface
-                    let oid = output.id().protocol_id();
-                    log::debug!("wayland - renderer assigned (output={oid}, surface={key})");
-                    // track wl-surface to rendering pipeline
-                    renderers.insert(key, renderer);
-                    self.lock_surfaces.push(lock_surface);
-                }
-                Err(err) => {
-                    self.error = Some(err.to_string());
-                    self.exit = true;
-                    break;
-                }
-            }
+            self.lock_output(conn, qh, &session_lock, &output);
         }
     }
 
@@ -243,23 +648,29 @@ impl SessionLockHandler for AppData {
         _qh: &QueueHandle<Self>,
         _session_lock: SessionLock,
     ) {
+        log::error!("wayland - session lock was rejected or revoked by the compositor");
+        self.error = Some("session lock finished by compositor".to_owned());
         self.exit = true;
     }
 
     fn configure(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
         session_lock_surface: SessionLockSurface,
         configure: SessionLockSurfaceConfigure,
         _serial: u32,
     ) {
         let (width, height) = configure.new_size;
-        let key = session_lock_surface.wl_surface().id().protocol_id();
-        self.modify(key, move |r| {
-            r.configure(width, height);
-            r.render();
-        });
+        let surface = session_lock_surface.wl_surface();
+        let key = surface.id().protocol_id();
+        if let Some(renderer) = self.software.get_mut(&key) {
+            renderer.configure(width, height);
+            renderer.render();
+            return;
+        }
+        self.modify(key, move |r| r.configure(width, height));
+        self.render_surface(qh, surface);
     }
 }
 
@@ -334,12 +745,15 @@ impl KeyboardHandler for AppData {
         _: &Connection,
         _: &QueueHandle<Self>,
         _: &wl_keyboard::WlKeyboard,
-        _surface: &wl_surface::WlSurface,
+        surface: &wl_surface::WlSurface,
         _: u32,
         _: &[u32],
         _keysyms: &[Keysym],
     ) {
-        log::debug!("wayland - keyboard enter");
+        let key = surface.id().protocol_id();
+        log::debug!("wayland - keyboard enter (surface={key})");
+        self.focused = Some(key);
+        self.focus();
     }
 
     fn leave(
@@ -363,7 +777,15 @@ impl KeyboardHandler for AppData {
     ) {
         if !self.settings.lock {
             log::info!("key pressed. exiting screensaver mode!");
-            self.exit = true;
+            self.begin_unlock();
+        }
+        // outputs drawn in software have no ui to edit the password
+        if self.settings.lock
+            && self
+                .active
+                .is_some_and(|key| self.software.contains_key(&key))
+        {
+            self.edit_password(&event);
         }
         let iced_event = keypress_event(event, self.modifiers, false);
         self.modify_all(|r| r.key_event(iced_event.clone()))
@@ -391,6 +813,9 @@ impl KeyboardHandler for AppData {
         _layout: u32,
     ) {
         self.modifiers = Some(modifiers);
+        for renderer in self.software.values_mut() {
+            renderer.set_caps_lock(modifiers.caps_lock);
+        }
         let iced_event = modifiers_event(modifiers);
         self.modify_all(|r| r.key_event(iced_event.clone()))
     }
@@ -404,12 +829,21 @@ impl PointerHandler for AppData {
         _pointer: &wl_pointer::WlPointer,
         events: &[PointerEvent],
     ) {
-        let events: Vec<_> = events.into_iter().map(|e| mouse_event(e)).collect();
-        self.modify_all(|r| {
-            for event in events.iter() {
-                r.mouse_event(event.clone());
-            }
-        })
+        // moving or clicking the pointer also ends the grace period
+        let input = events.iter().any(|event| {
+            matches!(
+                event.kind,
+                PointerEventKind::Motion { .. } | PointerEventKind::Press { .. }
+            )
+        });
+        if input && !self.settings.lock && self.settings.grace.is_some() {
+            log::info!("pointer moved. exiting screensaver mode!");
+            self.begin_unlock();
+        }
+        for event in events {
+            let key = event.surface.id().protocol_id();
+            self.modify(key, |r| r.mouse_event(mouse_event(event)));
+        }
     }
 }
 
@@ -437,10 +871,30 @@ impl CompositorHandler for AppData {
     fn frame(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
         _time: u32,
     ) {
+        let key = surface.id().protocol_id();
+        let interval = self.frame_interval;
+        let mut delay = None;
+        self.modify(key, |r| delay = r.frame_done(interval));
+        let Some(delay) = delay else {
+            self.render_surface(qh, surface);
+            return;
+        };
+        // delay rendering until the configured frame-rate cap allows it
+        let qh = qh.clone();
+        let surface = surface.clone();
+        self.loop_handle
+            .insert_source(Timer::from_duration(delay), move |_, _, app_data| {
+                app_data.modify(key, |r| {
+                    r.frame_done(None);
+                });
+                app_data.render_surface(&qh, &surface);
+                TimeoutAction::Drop
+            })
+            .expect("failed to schedule throttled frame");
     }
 
     fn surface_enter(
@@ -475,12 +929,20 @@ impl OutputHandler for AppData {
 
     fn new_output(
         &mut self,
-        _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
         output: wl_output::WlOutput,
     ) {
         let oid = output.id().protocol_id();
         log::debug!("wayland - new output (output={oid})");
+        // outputs plugged in while locked require their own lock surface
+        let Some(session_lock) = self.session_lock.clone() else {
+            return;
+        };
+        if session_lock.is_locked() && !self.lock_surfaces.contains_key(&oid) {
+            log::info!("locking newly connected output (output={oid})");
+            self.lock_output(conn, qh, &session_lock, &output);
+        }
     }
 
     fn update_output(
@@ -501,6 +963,7 @@ impl OutputHandler for AppData {
     ) {
         let oid = output.id().protocol_id();
         log::debug!("wayland - output destroyed (output={oid})");
+        self.unlock_output(&output);
     }
 }
 
@@ -1,63 +1,42 @@
 //! Dynamic ScreenLock CLI
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use rand::seq::IteratorRandom;
 
+mod auth;
 mod event;
 mod graphics;
+mod idle;
 mod lock;
+mod playlist;
+mod user;
 
 use clap_builder::Parser;
-use dynlock_lib::{Cli, Config, Settings};
+use dynlock_lib::{Cli, Command, Config, Settings, TextureSource};
+use playlist::Playlist;
 
 const XDG_PREFIX: &'static str = "dynlock";
 const DEFAULT_CONFIG: &'static str = "config.yaml";
 
-/// Attempt to Find Valid Filepath of Certain Extension
-fn find_file(p: &PathBuf, extensions: Vec<&str>) -> Result<PathBuf> {
-    if !p.exists() {
-        return Err(anyhow!(format!("no such file: {p:?}")));
-    }
-    Ok(if p.is_dir() {
-        let mut rng = rand::thread_rng();
-        let files = std::fs::read_dir(&p)
-            .context(format!("failed to read dir: {p:?}"))?
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter(|f| {
-                let ext = f
-                    .extension()
-                    .map(|s| s.to_string_lossy().to_string())
-                    .unwrap_or_default();
-                extensions.contains(&ext.as_str())
-            });
-        files
-            .choose(&mut rng)
-            .context(format!("no files present in dir {p:?}"))?
-    } else {
-        p.to_owned()
-    })
+/// Expand User Home in Configured Path
+fn expand(path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(path).to_string())
 }
 
 /// Find PathBuf from Option or Use XDG to Find Default
 fn find_config_path(path: Option<String>, default_name: &str) -> PathBuf {
-    path.clone()
-        .map(|v| shellexpand::tilde(&v.to_string()).to_string())
-        .map(|v| PathBuf::from(v))
-        .unwrap_or_else(|| {
-            xdg::BaseDirectories::with_prefix(XDG_PREFIX)
-                .expect("failed to read xdg base-dirs")
-                .get_config_file(default_name)
-        })
+    path.map(|v| expand(&v)).unwrap_or_else(|| {
+        xdg::BaseDirectories::with_prefix(XDG_PREFIX)
+            .expect("failed to read xdg base-dirs")
+            .get_config_file(default_name)
+    })
 }
 
-/// Prepare CLI Flags for Use and Generate Settings
-pub fn settings(cli: Cli) -> Result<Settings> {
-    // read configuration file according to settings
-    let cfgpath = find_config_path(cli.config, DEFAULT_CONFIG);
-    let config = match cfgpath.exists() {
+/// Read Configuration File (or Use the Default Configuration)
+fn read_config(path: Option<String>) -> Result<Config> {
+    let cfgpath = find_config_path(path, DEFAULT_CONFIG);
+    let mut config: Config = match cfgpath.exists() {
         true => {
             log::info!("reading configuration: {cfgpath:?}");
             let cfgdata = std::fs::read_to_string(&cfgpath).context("failed to read config")?;
@@ -68,31 +47,174 @@ pub fn settings(cli: Cli) -> Result<Settings> {
             Config::default()
         }
     };
-    // resolve fragment shader path
-    let shader = cli.shader.or(config.shader);
-    let fragpath = find_config_path(shader, "shaders");
-    let fragment = find_file(&fragpath, vec!["glsl"]).context("failed to find shader")?;
+    config
+        .validate()
+        .map_err(|err| anyhow!("invalid config: {err}"))?;
+    // texture overrides are relative to the config rather than the working directory
+    let dir = cfgpath.parent().unwrap_or(Path::new("."));
+    for source in config.textures.values_mut().flatten() {
+        if let TextureSource::Image(path) = source {
+            *path = dir.join(expand(&path.to_string_lossy()));
+        }
+    }
+    Ok(config)
+}
+
+/// Build Playlist from CLI Flags (which take priority) and Configuration
+fn playlist(cli: &Cli, config: &Config) -> Result<Playlist> {
+    let shaders: Vec<PathBuf> = match (&cli.shader, config.playlist.shaders.is_empty()) {
+        (Some(shader), _) => vec![expand(shader)],
+        (None, false) => config.playlist.shaders.iter().map(|s| expand(s)).collect(),
+        (None, true) => vec![find_config_path(config.shader.clone(), "shaders")],
+    };
+    let backgrounds: Vec<PathBuf> = match (&cli.background, config.playlist.backgrounds.is_empty())
+    {
+        (Some(bg), _) => vec![expand(bg)],
+        (None, false) => config
+            .playlist
+            .backgrounds
+            .iter()
+            .map(|b| expand(b))
+            .collect(),
+        (None, true) => config.background.iter().map(|b| expand(b)).collect(),
+    };
+    Playlist::new(&shaders, &backgrounds, config.playlist.clone(), cli.seed)
+}
+
+/// Prepare CLI Flags for Use and Generate Settings
+pub fn settings(cli: Cli) -> Result<(Settings, Playlist)> {
+    let config = read_config(cli.config.clone())?;
+    let mut playlist = playlist(&cli, &config)?;
+    // resolve initial fragment shader and background image
+    let fragment = playlist.next_shader().context("failed to find shader")?;
     log::info!("loading fragment shader: {fragment:?}");
-    // resolve background image path
-    let mut background = cli.background.or(config.background).map(PathBuf::from);
-    if let Some(bg) = background {
-        let bpath =
-            find_file(&bg, vec!["png", "jpg", "jpeg"]).context("failed to find background")?;
-        log::info!("loading background image: {bpath:?}");
-        background = Some(bpath);
+    let background = playlist.next_background();
+    if let Some(bg) = background.as_ref() {
+        log::info!("loading background image: {bg:?}");
+    }
+    // shaders fallback to the lock background when no wallpaper is configured
+    let wallpaper = config
+        .wallpaper
+        .map(|path| expand(&path))
+        .or_else(|| background.clone());
+    let avatar = user::avatar_path(config.theme.avatar.as_deref());
+    if let Some(avatar) = avatar.as_ref() {
+        log::info!("using avatar image: {avatar:?}");
     }
-    // load Shader from file (if present)
-    let shader = std::fs::read_to_string(fragment).context("failed to read shader file")?;
     let lock = !cli.screensave.unwrap_or(!config.lock);
     match lock {
         true => log::info!("running in screensaver mode!"),
         false => log::info!("running in lockscreen mode!"),
     }
-    Ok(Settings {
+    // locks may start as a screensaver dismissable without a password
+    let grace = dynlock_lib::duration("grace", cli.grace.unwrap_or(config.grace))
+        .map_err(|err| anyhow!("invalid grace period: {err}"))?;
+    let grace = match lock && !grace.is_zero() {
+        true => Some(grace),
+        false => None,
+    };
+    if let Some(grace) = grace {
+        log::info!("requiring a password after a {grace:?} grace period");
+    }
+    // hot-reloading must never weaken a real lock
+    if cli.watch && lock {
+        return Err(anyhow!("--watch is only available in screensaver mode"));
+    }
+    log::info!("using pam service: {:?}", config.auth.service);
+    let settings = Settings {
         lock,
-        shader,
+        grace,
+        shader_path: fragment,
         background,
-    })
+        wallpaper,
+        avatar,
+        max_fps: config.max_fps,
+        watch: cli.watch,
+        auth: config.auth,
+        animation: config.animation,
+        renderer: config.renderer,
+        params: config.params,
+        textures: config.textures,
+        playlist: config.playlist,
+        theme: config.theme,
+    };
+    Ok((settings, playlist))
+}
+
+/// Print Shaders and Backgrounds Considered by Discovery
+fn list_shaders(cli: Cli) -> Result<()> {
+    let config = read_config(cli.config.clone())?;
+    let playlist = playlist(&cli, &config)?;
+    for (path, weight) in playlist.shaders() {
+        let name = graphics::ShaderPack::name(path);
+        match weight {
+            w if w <= 0.0 => println!("{name}\t{} (excluded)", path.display()),
+            w if w != 1.0 => println!("{name}\t{} (weight {w})", path.display()),
+            _ => println!("{name}\t{}", path.display()),
+        }
+    }
+    for path in playlist.backgrounds() {
+        println!("background\t{}", path.display());
+    }
+    Ok(())
+}
+
+/// Fork and Daemonize Process Guarded by the Specified Pid-File
+fn daemonize(name: &str) -> Result<()> {
+    // find pid location for daemonization
+    let pid = xdg::BaseDirectories::new()
+        .context("failed to read xdg base-dirs")?
+        .get_runtime_file(name)
+        .context("failed to locate lockfile")?;
+    let daemon = daemonize::Daemonize::new().pid_file(pid);
+    daemon.start().context("failed to daemonize")
+}
+
+/// Validate Fragment Shader and Print Compiler Diagnostics
+fn check_shader(path: String) -> Result<()> {
+    let path = expand(&path);
+    match graphics::check_shader(&path) {
+        Ok(pack) => {
+            println!("{}: ok", path.display());
+            let meta = &pack.meta;
+            let fields = [
+                ("name", meta.name.clone()),
+                ("author", meta.author.clone()),
+                ("license", meta.license.clone()),
+                (
+                    "preview",
+                    meta.preview.as_ref().map(|p| p.display().to_string()),
+                ),
+            ];
+            for (field, value) in fields.into_iter() {
+                if let Some(value) = value {
+                    println!("  {field}: {value}");
+                }
+            }
+            for param in meta.params.iter() {
+                let range = match (param.min, param.max) {
+                    (None, None) => String::new(),
+                    (min, max) => format!(
+                        " [{}..{}]",
+                        min.map(|v| v.to_string()).unwrap_or_default(),
+                        max.map(|v| v.to_string()).unwrap_or_default()
+                    ),
+                };
+                println!(
+                    "  param {}: {:?} = {:?}{range} {}",
+                    param.name,
+                    param.kind,
+                    param.default,
+                    param.description.as_deref().unwrap_or_default()
+                );
+            }
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        }
+    }
 }
 
 fn main() -> Result<()> {
@@ -113,22 +235,30 @@ fn main() -> Result<()> {
         }
     };
 
+    // run utility subcommands instead of locking
+    if matches!(cli.command, Some(Command::Idle)) {
+        let config = read_config(cli.config.clone())?;
+        if cli.daemonize {
+            daemonize("dynlock-idle.lock")?;
+        }
+        return idle::idle(cli, config.idle);
+    }
+    if let Some(Command::CheckShader { path }) = cli.command {
+        return check_shader(path);
+    }
+    if cli.list_shaders {
+        return list_shaders(cli);
+    }
+
     // convert cli flags into settings object
-    let daemonize = cli.daemonize;
-    let settings = settings(cli)?;
+    let daemon = cli.daemonize;
+    let (settings, playlist) = settings(cli)?;
 
     // ensure only one lock instance runs at a time
-    if daemonize {
-        // find pid location for daemonization
-        let pid = xdg::BaseDirectories::new()
-            .context("failed to read xdg base-dirs")?
-            .get_runtime_file("dynlock.lock")
-            .context("failed to locate lockfile")?;
-        // daemonize
-        let daemon = daemonize::Daemonize::new().pid_file(pid);
-        daemon.start().context("failed to daemonize")?;
+    if daemon {
+        daemonize("dynlock.lock")?;
     }
 
     // attempt to load shader from file
-    lock::lock(settings)
+    lock::lock(settings, playlist)
 }
@@ -0,0 +1,454 @@
+//! Shader and Background Playlist Selection
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use dynlock_lib::{PlaylistConfig, PlaylistOrder, PowerCondition};
+
+use crate::graphics::ShaderPack;
+
+/// File Extensions of Discoverable Shaders
+pub const SHADER_EXTENSIONS: &[&str] = &["glsl", "wgsl"];
+
+/// File Extensions of Discoverable Background Images
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// Filename of the Selection History within the XDG State Directory
+const HISTORY_FILE: &str = "history.yaml";
+
+/// Expand Path into Files of Certain Extension
+///
+/// Files and shader packs are returned as-is while directories
+/// are searched (non-recursively) in sorted order.
+pub fn discover(path: &Path, extensions: &[&str]) -> Result<Vec<PathBuf>> {
+    if !path.exists() {
+        return Err(anyhow!("no such file: {path:?}"));
+    }
+    if !path.is_dir() || ShaderPack::is_pack(path) {
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+        .context(format!("failed to read dir: {path:?}"))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|f| {
+            let ext = f
+                .extension()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            extensions.contains(&ext.as_str()) || ShaderPack::is_pack(f)
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Check if the System is Running from a Discharging Battery
+fn on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    supplies.filter_map(|e| e.ok()).any(|entry| {
+        let read =
+            |name: &str| std::fs::read_to_string(entry.path().join(name)).unwrap_or_default();
+        read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+    })
+}
+
+/// Exclude Recently Chosen Entries while Other Candidates Remain
+fn avoid(weights: &mut [f32], recent: &[usize]) {
+    let others = (weights.iter().enumerate()).any(|(i, w)| !recent.contains(&i) && *w > 0.0);
+    if others {
+        recent.iter().for_each(|i| weights[*i] = 0.0);
+    }
+}
+
+/// Choose the Next Entry Index from Weighted Candidates
+///
+/// Shuffled playlists avoid repeating the current entry when possible.
+fn choose(
+    order: PlaylistOrder,
+    weights: &[f32],
+    current: Option<usize>,
+    rng: &mut StdRng,
+) -> Option<usize> {
+    match order {
+        PlaylistOrder::Ordered => {
+            let start = current.map(|i| i + 1).unwrap_or(0);
+            (0..weights.len())
+                .map(|n| (start + n) % weights.len())
+                .find(|i| weights[*i] > 0.0)
+        }
+        PlaylistOrder::Shuffle => {
+            let mut weights = weights.to_vec();
+            avoid(&mut weights, &current.into_iter().collect::<Vec<_>>());
+            let dist = WeightedIndex::new(&weights).ok()?;
+            Some(dist.sample(rng))
+        }
+    }
+}
+
+/// Recently Chosen Shaders and Backgrounds (Most Recent Last)
+///
+/// Persisted between runs so a reported shader can be found again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub seed: Option<u64>,
+    pub shaders: Vec<PathBuf>,
+    pub backgrounds: Vec<PathBuf>,
+}
+
+impl History {
+    /// Location of the History File
+    fn path() -> Result<PathBuf> {
+        xdg::BaseDirectories::with_prefix(crate::XDG_PREFIX)
+            .context("failed to read xdg base-dirs")?
+            .place_state_file(HISTORY_FILE)
+            .context("failed to create state directory")
+    }
+
+    /// Load Previous History (Empty if Missing or Invalid)
+    pub fn load() -> Self {
+        let history = Self::path().and_then(|path| {
+            let data = std::fs::read_to_string(&path).context("failed to read history")?;
+            serde_yaml::from_str(&data).context("failed to parse history")
+        });
+        history.unwrap_or_else(|err| {
+            log::debug!("playlist - no previous history: {err:#}");
+            Self::default()
+        })
+    }
+
+    /// Write History to the XDG State Directory
+    fn save(&self) {
+        let result = Self::path().and_then(|path| {
+            let data = serde_yaml::to_string(self).context("failed to encode history")?;
+            std::fs::write(&path, data).context(format!("failed to write {path:?}"))
+        });
+        if let Err(err) = result {
+            log::warn!("playlist - failed to save history: {err:#}");
+        }
+    }
+
+    /// Append Chosen Entry and Trim to the Specified Length
+    fn record(entries: &mut Vec<PathBuf>, path: &Path, limit: usize) {
+        entries.push(path.to_owned());
+        let excess = entries.len().saturating_sub(limit.max(1));
+        entries.drain(..excess);
+    }
+}
+
+/// Rotating Selection of Shaders and Backgrounds
+pub struct Playlist {
+    config: PlaylistConfig,
+    shaders: Vec<PathBuf>,
+    backgrounds: Vec<PathBuf>,
+    shader: Option<usize>,
+    background: Option<usize>,
+    history: History,
+    seeded: bool,
+    rng: StdRng,
+}
+
+impl Playlist {
+    /// Discover Candidate Shaders and Backgrounds from Playlist Entries
+    ///
+    /// Explicit seeds reproduce the same selections regardless of history.
+    pub fn new(
+        shaders: &[PathBuf],
+        backgrounds: &[PathBuf],
+        config: PlaylistConfig,
+        seed: Option<u64>,
+    ) -> Result<Self> {
+        let shaders = shaders
+            .iter()
+            .map(|path| discover(path, SHADER_EXTENSIONS).context("failed to find shader"))
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+        let backgrounds = backgrounds
+            .iter()
+            .map(|path| discover(path, IMAGE_EXTENSIONS).context("failed to find background"))
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+        if shaders.is_empty() {
+            return Err(anyhow!("no shaders present in playlist"));
+        }
+        // ordered playlists resume after the last recorded entries
+        let mut history = History::load();
+        let last = |entries: &[PathBuf], history: &[PathBuf]| match seed.is_some() {
+            true => None,
+            false => history
+                .last()
+                .and_then(|l| entries.iter().position(|p| p == l)),
+        };
+        let (shader, background) = match config.order {
+            PlaylistOrder::Ordered => (
+                last(&shaders, &history.shaders),
+                last(&backgrounds, &history.backgrounds),
+            ),
+            PlaylistOrder::Shuffle => (None, None),
+        };
+        let seeded = seed.is_some();
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        log::info!("playlist - selection seed: {seed}");
+        history.seed = Some(seed);
+        Ok(Self {
+            config,
+            shaders,
+            backgrounds,
+            shader,
+            background,
+            history,
+            seeded,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    /// Indexes of Recently Chosen Entries to Avoid Repeating
+    fn recent(&self, entries: &[PathBuf], history: &[PathBuf]) -> Vec<usize> {
+        if self.seeded {
+            return vec![];
+        }
+        let skip = history.len().saturating_sub(self.config.avoid_recent);
+        (history.iter().skip(skip))
+            .filter_map(|h| entries.iter().position(|p| p == h))
+            .collect()
+    }
+
+    /// Discovered Shaders alongside their Current Selection Weights
+    pub fn shaders(&self) -> Vec<(&Path, f32)> {
+        let battery = on_battery();
+        (self.shaders.iter())
+            .map(|path| (path.as_path(), self.weight(path, battery)))
+            .collect()
+    }
+
+    /// Discovered Background Images
+    #[inline]
+    pub fn backgrounds(&self) -> &[PathBuf] {
+        &self.backgrounds
+    }
+
+    /// Selection Weight of a Shader after Applying Matching Rules
+    fn weight(&self, path: &Path, battery: bool) -> f32 {
+        let name = ShaderPack::name(path);
+        self.config
+            .rules
+            .iter()
+            .filter(|rule| rule.shaders.contains(&name))
+            .filter(|rule| match rule.when {
+                PowerCondition::Always => true,
+                PowerCondition::OnBattery => battery,
+                PowerCondition::OnAc => !battery,
+            })
+            .map(|rule| rule.weight.max(0.0))
+            .product()
+    }
+
+    /// Advance to the Next Shader in the Playlist
+    pub fn next_shader(&mut self) -> Option<PathBuf> {
+        let path = self.select_shader(on_battery())?;
+        log::info!("playlist - chose shader {path:?}");
+        self.history.save();
+        Some(path)
+    }
+
+    /// Choose the Next Shader and Record it in the History
+    fn select_shader(&mut self, battery: bool) -> Option<PathBuf> {
+        let mut weights: Vec<f32> = (self.shaders.iter())
+            .map(|path| self.weight(path, battery))
+            .collect();
+        if weights.iter().all(|w| *w <= 0.0) {
+            log::warn!("playlist - every shader is excluded, ignoring rules");
+            weights = vec![1.0; self.shaders.len()];
+        }
+        if self.config.order == PlaylistOrder::Shuffle {
+            avoid(
+                &mut weights,
+                &self.recent(&self.shaders, &self.history.shaders),
+            );
+        }
+        self.shader = choose(self.config.order, &weights, self.shader, &mut self.rng);
+        let path = self.shader.map(|i| self.shaders[i].to_owned())?;
+        History::record(&mut self.history.shaders, &path, self.config.avoid_recent);
+        Some(path)
+    }
+
+    /// Advance to the Next Background in the Playlist (if any)
+    pub fn next_background(&mut self) -> Option<PathBuf> {
+        let path = self.select_background()?;
+        log::info!("playlist - chose background {path:?}");
+        self.history.save();
+        Some(path)
+    }
+
+    /// Choose the Next Background and Record it in the History
+    fn select_background(&mut self) -> Option<PathBuf> {
+        let mut weights = vec![1.0; self.backgrounds.len()];
+        if self.config.order == PlaylistOrder::Shuffle {
+            avoid(
+                &mut weights,
+                &self.recent(&self.backgrounds, &self.history.backgrounds),
+            );
+        }
+        self.background = choose(self.config.order, &weights, self.background, &mut self.rng);
+        let path = self.background.map(|i| self.backgrounds[i].to_owned())?;
+        History::record(
+            &mut self.history.backgrounds,
+            &path,
+            self.config.avoid_recent,
+        );
+        Some(path)
+    }
+
+    /// Check if Rotating the Playlist would Change the Background
+    #[inline]
+    pub fn rotates_backgrounds(&self) -> bool {
+        self.backgrounds.len() > 1
+    }
+
+    /// Shader Pinned to the Specified Output (by Shader Name or Path)
+    pub fn output_shader(&self, output: &str) -> Option<PathBuf> {
+        let value = self.config.outputs.get(output)?;
+        let path = PathBuf::from(shellexpand::tilde(value).to_string());
+        if path.exists() {
+            return Some(path);
+        }
+        let found = (self.shaders.iter())
+            .find(|path| &ShaderPack::name(path) == value)
+            .cloned();
+        if found.is_none() {
+            log::warn!("playlist - unknown shader {value:?} pinned to output {output:?}");
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use dynlock_lib::PlaylistRule;
+
+    /// Build Playlist over In-Memory Entries without Touching the Filesystem
+    fn playlist(names: &[&str], config: PlaylistConfig, seed: Option<u64>) -> Playlist {
+        Playlist {
+            config,
+            shaders: names
+                .iter()
+                .map(|name| PathBuf::from(format!("{name}.glsl")))
+                .collect(),
+            backgrounds: names
+                .iter()
+                .map(|name| PathBuf::from(format!("{name}.png")))
+                .collect(),
+            shader: None,
+            background: None,
+            history: History::default(),
+            seeded: seed.is_some(),
+            rng: StdRng::seed_from_u64(seed.unwrap_or_default()),
+        }
+    }
+
+    /// Pick a Number of Shaders in a Row
+    fn picks(playlist: &mut Playlist, count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|_| playlist.select_shader(false).expect("no shader chosen"))
+            .collect()
+    }
+
+    const NAMES: [&str; 5] = ["a", "b", "c", "d", "e"];
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let config = PlaylistConfig::default();
+        let first = picks(&mut playlist(&NAMES, config.clone(), Some(42)), 20);
+        let second = picks(&mut playlist(&NAMES, config.clone(), Some(42)), 20);
+        assert_eq!(first, second);
+        let other = picks(&mut playlist(&NAMES, config, Some(7)), 20);
+        assert_ne!(first, other);
+        // shuffling never repeats the current shader back to back
+        assert!(first.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn shuffle_avoids_recent_history() {
+        let config = PlaylistConfig {
+            avoid_recent: 3,
+            ..Default::default()
+        };
+        for seed in 0..50 {
+            let mut playlist = playlist(&NAMES, config.clone(), None);
+            playlist.rng = StdRng::seed_from_u64(seed);
+            playlist.history.shaders = ["a", "b", "c"]
+                .iter()
+                .map(|name| PathBuf::from(format!("{name}.glsl")))
+                .collect();
+            let path = playlist.select_shader(false).expect("no shader chosen");
+            assert!(["d.glsl", "e.glsl"].contains(&path.to_str().unwrap()));
+            assert_eq!(playlist.history.shaders.len(), 3);
+            assert_eq!(playlist.history.shaders.last(), Some(&path));
+        }
+    }
+
+    #[test]
+    fn shuffle_allows_recent_when_nothing_else_remains() {
+        let config = PlaylistConfig {
+            avoid_recent: 5,
+            ..Default::default()
+        };
+        let mut playlist = playlist(&["a", "b"], config, None);
+        assert_eq!(picks(&mut playlist, 4).len(), 4);
+    }
+
+    #[test]
+    fn rules_exclude_and_weight_shaders() {
+        let config = PlaylistConfig {
+            rules: vec![PlaylistRule {
+                shaders: vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+                when: PowerCondition::Always,
+                weight: 0.0,
+            }],
+            ..Default::default()
+        };
+        let mut playlist = playlist(&NAMES, config, Some(1));
+        let picked = picks(&mut playlist, 20);
+        assert!(picked
+            .iter()
+            .all(|path| ["d.glsl", "e.glsl"].contains(&path.to_str().unwrap())));
+        // excluding every shader ignores the rules instead of choosing nothing
+        playlist.config.rules[0].shaders = NAMES.iter().map(|name| name.to_string()).collect();
+        assert!(playlist.select_shader(false).is_some());
+    }
+
+    #[test]
+    fn ordered_playlist_wraps_around() {
+        let config = PlaylistConfig {
+            order: PlaylistOrder::Ordered,
+            ..Default::default()
+        };
+        let mut playlist = playlist(&["a", "b", "c"], config, None);
+        playlist.shader = Some(1);
+        let names: Vec<String> = (picks(&mut playlist, 4).iter())
+            .map(|path| ShaderPack::name(path))
+            .collect();
+        assert_eq!(names, ["c", "a", "b", "c"]);
+        assert_eq!(playlist.select_background(), Some(PathBuf::from("a.png")));
+    }
+
+    #[test]
+    fn history_is_trimmed_to_limit() {
+        let mut entries = vec![];
+        for name in NAMES {
+            History::record(&mut entries, Path::new(name), 3);
+        }
+        assert_eq!(entries, [Path::new("c"), Path::new("d"), Path::new("e")]);
+        History::record(&mut entries, Path::new("f"), 0);
+        assert_eq!(entries, [Path::new("f")]);
+    }
+}
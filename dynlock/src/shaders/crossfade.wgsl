@@ -0,0 +1,34 @@
+// Crossfade: blends the outgoing shader (t_channel0) into the incoming one (t_channel1).
+
+struct FrameUniforms {
+    iResolution: vec3<f32>,
+    iTime: f32,
+    iTimeDelta: f32,
+    iFrame: i32,
+    iFrameRate: f32,
+    iFadeAmount: f32,
+    iMouse: vec4<f32>,
+    iDate: vec4<f32>,
+    iFailedAttempts: f32,
+    iAuthenticating: f32,
+    iPasswordLength: f32,
+    iKeystrokeTime: f32,
+    iCapsLock: f32,
+    iFailurePulse: f32,
+    iUnlockProgress: f32,
+    iBufferPass: f32,
+}
+
+@group(0) @binding(1) var s_screenshot: sampler;
+@group(0) @binding(2) var<uniform> u: FrameUniforms;
+@group(0) @binding(3) var t_channel0: texture_2d<f32>;
+@group(0) @binding(4) var t_channel1: texture_2d<f32>;
+
+@fragment
+fn main(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = coord.xy / u.iResolution.xy;
+    let previous = textureSample(t_channel0, s_screenshot, uv);
+    let current = textureSample(t_channel1, s_screenshot, uv);
+    // iFadeAmount holds the crossfade progress rather than the lock fade
+    return mix(previous, current, u.iFadeAmount);
+}
@@ -9,10 +9,23 @@ layout(location=0) out vec4 f_color;
 layout(set = 0, binding = 0) uniform texture2D t_screenshot;
 layout(set = 0, binding = 1) uniform sampler s_screenshot;
 
-layout(push_constant) uniform FrameUniforms {
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
     float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
     float iFadeAmount;
-    vec2  iResolution;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
 };
 
 vec3 colorA = vec3(0.149,0.141,0.912);
@@ -23,5 +36,6 @@ void main() {
     float pct  = abs(sin(iTime));
     color      = mix(colorA, colorB, pct);
     f_color    = vec4(color,1.0);
+    // fade towards the desktop while locking/unlocking
+    f_color = mix(f_color, texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy), iFadeAmount);
 }
-
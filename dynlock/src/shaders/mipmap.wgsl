@@ -0,0 +1,13 @@
+// Mipmap: downsamples the previous mip level (t_source) into the next one.
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@fragment
+fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // mip levels are half the size of the previous level (rounded down)
+    let size = max(textureDimensions(t_source) / 2u, vec2<u32>(1u));
+    return textureSample(t_source, s_source, position.xy / vec2<f32>(size));
+}
@@ -0,0 +1,65 @@
+#version 450
+// Shadertoy compatibility prelude prepended to shaders that only
+// define `mainImage` rather than their own `main` entrypoint.
+
+layout(location=0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform texture2D t_screenshot;
+layout(set = 0, binding = 1) uniform sampler s_screenshot;
+
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
+    float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
+    float iFadeAmount;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
+};
+
+// shader pack pass inputs (the screenshot when unused)
+layout(set = 0, binding = 3) uniform texture2D t_channel0;
+layout(set = 0, binding = 4) uniform texture2D t_channel1;
+layout(set = 0, binding = 5) uniform texture2D t_channel2;
+layout(set = 0, binding = 6) uniform texture2D t_channel3;
+
+#define iChannel0 sampler2D(t_channel0, s_screenshot)
+#define iChannel1 sampler2D(t_channel1, s_screenshot)
+#define iChannel2 sampler2D(t_channel2, s_screenshot)
+#define iChannel3 sampler2D(t_channel3, s_screenshot)
+
+// extra textures declared in the shader manifest (the screenshot when unused)
+layout(set = 0, binding = 8) uniform texture2D t_texture0;
+layout(set = 0, binding = 9) uniform texture2D t_texture1;
+layout(set = 0, binding = 10) uniform texture2D t_texture2;
+layout(set = 0, binding = 11) uniform texture2D t_texture3;
+
+#define iTexture0 sampler2D(t_texture0, s_screenshot)
+#define iTexture1 sampler2D(t_texture1, s_screenshot)
+#define iTexture2 sampler2D(t_texture2, s_screenshot)
+#define iTexture3 sampler2D(t_texture3, s_screenshot)
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+
+void main() {
+    // shadertoy places the origin in the bottom-left corner, buffers are
+    // stored unflipped so sampling them with `fragCoord` stays consistent
+    vec2 fragCoord = vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y);
+    if (iBufferPass > 0.5) {
+        fragCoord = gl_FragCoord.xy;
+    }
+    mainImage(f_color, fragCoord);
+    // fade towards the desktop while locking/unlocking
+    vec4 desktop = texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy);
+    f_color = mix(f_color, desktop, iFadeAmount);
+}
+
@@ -0,0 +1,14 @@
+// display made up of two opposite triangles
+var<private> positions: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0,  1.0), //c
+    vec2<f32>(-1.0, -1.0), //a
+    vec2<f32>( 1.0, -1.0), //b
+    vec2<f32>(-1.0,  1.0), //c
+    vec2<f32>( 1.0, -1.0), //b
+    vec2<f32>( 1.0,  1.0), //d
+);
+
+@vertex
+fn main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(positions[index], 0.0, 1.0);
+}
@@ -0,0 +1,57 @@
+//! Locked User Details Shown in the UI
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+/// Directory of Per-User Icons Managed by AccountsService
+const ACCOUNTS_SERVICE_ICONS: &str = "/var/lib/AccountsService/icons";
+
+/// Full Name of the User (from GECOS) Falling Back to the Username
+pub fn realname() -> String {
+    let name = whoami::realname();
+    match name.trim().is_empty() {
+        true => whoami::username(),
+        false => name.trim().to_owned(),
+    }
+}
+
+/// Find Avatar Image of the Current User
+///
+/// Searches the configured path, the AccountsService icon and `~/.face`.
+pub fn avatar_path(configured: Option<&str>) -> Option<PathBuf> {
+    let configured = configured.map(|path| PathBuf::from(shellexpand::tilde(path).to_string()));
+    let candidates = [
+        configured,
+        Some(Path::new(ACCOUNTS_SERVICE_ICONS).join(whoami::username())),
+        Some(PathBuf::from(shellexpand::tilde("~/.face").to_string())),
+        Some(PathBuf::from(
+            shellexpand::tilde("~/.face.icon").to_string(),
+        )),
+    ];
+    candidates.into_iter().flatten().find(|path| path.is_file())
+}
+
+/// Load Avatar Image Cropped into an Anti-Aliased Circle of the Given Size
+pub fn avatar(path: &Path, size: u32) -> Result<RgbaImage> {
+    let img = std::fs::read(path).context(format!("failed to read avatar {path:?}"))?;
+    let rgba = image::load_from_memory(&img)
+        .context(format!("invalid avatar {path:?}"))?
+        .to_rgba8();
+    // crop the centered square before scaling
+    let (width, height) = rgba.dimensions();
+    let side = width.min(height);
+    let square = imageops::crop_imm(&rgba, (width - side) / 2, (height - side) / 2, side, side);
+    let size = size.max(1);
+    let mut avatar = imageops::resize(&square.to_image(), size, size, FilterType::Triangle);
+    // mask everything outside of the circle with a one pixel soft edge
+    let radius = size as f32 / 2.0;
+    for (x, y, pixel) in avatar.enumerate_pixels_mut() {
+        let dx = x as f32 + 0.5 - radius;
+        let dy = y as f32 + 0.5 - radius;
+        let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+        pixel[3] = (pixel[3] as f32 * coverage) as u8;
+    }
+    Ok(avatar)
+}
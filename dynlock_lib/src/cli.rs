@@ -1,5 +1,5 @@
 ///! CLI Definitions for Dynlock
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// Dynamic and Configurable Wayland Lockscreen
 ///
@@ -13,7 +13,7 @@ pub struct Cli {
     /// Defaults to `$XDG_CONFIG_DIR/dynlock/config.yaml` (if present)
     #[clap(short, long)]
     pub config: Option<String>,
-    /// Fragment shader file/search-directory
+    /// Fragment shader file, shader pack or search-directory
     ///
     /// Defaults to `$XDG_CONFIG_DIR/dynlock/shaders` directory
     #[clap(short, long)]
@@ -23,11 +23,29 @@ pub struct Cli {
     /// Defaults to a Live Screenshot of the current screen
     #[clap(short, long)]
     pub background: Option<String>,
+    /// Seed used to randomly select shaders and backgrounds
+    ///
+    /// The seed of every run is logged and saved to `$XDG_STATE_HOME/dynlock`
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// List the shaders and backgrounds discovery would choose from
+    #[clap(long)]
+    pub list_shaders: bool,
     /// Screensaver mode does not lock
     ///
     /// The Default Mode without A Configuration File is Lock Mode
     #[clap(long)]
     pub screensave: Option<bool>,
+    /// Seconds the lock runs as a screensaver before requiring a password
+    ///
+    /// Overrides the configured grace period (0 disables it)
+    #[clap(long)]
+    pub grace: Option<f32>,
+    /// Reload the shader whenever its files change
+    ///
+    /// Only available in screensaver mode for shader development
+    #[clap(short, long)]
+    pub watch: bool,
     /// Fork and daemonize process if enabled
     ///
     /// Useful for preventing more than once instance from running at once
@@ -36,4 +54,22 @@ pub struct Cli {
     /// Optional Logfile for Logging Output
     #[clap(short, long)]
     pub logfile: Option<String>,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Additional Dynlock Utilities
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Validate a fragment shader or shader pack and print compiler diagnostics
+    ///
+    /// Exits with a non-zero status if the shader fails to compile
+    CheckShader {
+        /// Fragment shader filepath (.glsl or .wgsl) or shader pack directory
+        path: String,
+    },
+    /// Run as an idle daemon starting the screensaver and lockscreen
+    ///
+    /// Timeouts are configured in the `idle` section of the configuration
+    Idle,
 }
@@ -1,29 +1,633 @@
 ///! Lockscreen Configuration Settings
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use serde::Deserialize;
 
+/// Longest Duration Accepted for any Configured Timeout
+const MAX_SECONDS: f32 = 365.0 * 24.0 * 60.0 * 60.0;
+
 #[inline]
 fn _true() -> bool {
     true
 }
 
+/// Convert Configured Seconds into a Duration (Rejecting Invalid Values)
+pub fn duration(name: &str, value: f32) -> Result<Duration, String> {
+    match (0.0..=MAX_SECONDS).contains(&value) {
+        true => Ok(seconds(value)),
+        false => Err(format!(
+            "{name} must be a number of seconds between 0 and {MAX_SECONDS} (got {value})"
+        )),
+    }
+}
+
+/// Convert Validated Seconds into a Duration (Negative Values become Zero)
+#[inline]
+fn seconds(value: f32) -> Duration {
+    Duration::try_from_secs_f32(value.max(0.0)).unwrap_or_default()
+}
+
 /// Configuration Settings for Dynlock
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct Config {
     #[serde(default = "_true")]
     pub lock: bool,
+    /// Seconds the lock starts as a screensaver dismissable without a password
+    pub grace: f32,
     pub shader: Option<String>,
     pub background: Option<String>,
+    /// Desktop wallpaper image available to shaders as a texture
+    pub wallpaper: Option<String>,
+    /// Maximum frames rendered per second on each output
+    pub max_fps: Option<u32>,
+    pub auth: AuthConfig,
+    pub animation: AnimationConfig,
+    pub renderer: RendererConfig,
+    /// Shader parameter overrides keyed by shader name
+    pub params: BTreeMap<String, BTreeMap<String, ParamValue>>,
+    /// Shader texture inputs keyed by shader name (replaces the manifest's)
+    pub textures: BTreeMap<String, Vec<TextureSource>>,
+    pub playlist: PlaylistConfig,
+    pub theme: ThemeConfig,
+    pub idle: IdleConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             lock: true,
+            grace: 0.0,
             shader: None,
             background: None,
+            wallpaper: None,
+            max_fps: None,
+            auth: AuthConfig::default(),
+            animation: AnimationConfig::default(),
+            renderer: RendererConfig::default(),
+            params: BTreeMap::new(),
+            textures: BTreeMap::new(),
+            playlist: PlaylistConfig::default(),
+            theme: ThemeConfig::default(),
+            idle: IdleConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reject Timeouts that cannot be Represented as a Duration
+    pub fn validate(&self) -> Result<(), String> {
+        // negative animation durations are allowed and disable the animation
+        let animation = |value: f32| match value < 0.0 {
+            true => 0.0,
+            false => value,
+        };
+        let fields = [
+            ("grace", self.grace),
+            ("auth.backoff", self.auth.backoff),
+            ("auth.backoff_max", self.auth.backoff_max),
+            ("animation.fade_in", animation(self.animation.fade_in)),
+            ("animation.fade_out", animation(self.animation.fade_out)),
+            ("playlist.interval", self.playlist.interval * 60.0),
+            ("playlist.crossfade", animation(self.playlist.crossfade)),
+            ("idle.screensaver", self.idle.screensaver),
+            ("idle.lock", self.idle.lock),
+        ];
+        for (name, value) in fields {
+            duration(name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Authentication Configuration Settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// PAM service name found in `/etc/pam.d` or `/usr/lib/pam.d`
+    pub service: String,
+    /// Delay in seconds after a failed attempt (doubles every failure)
+    pub backoff: f32,
+    /// Maximum delay in seconds between failed attempts
+    pub backoff_max: f32,
+}
+
+impl AuthConfig {
+    /// Delay after the First Failed Attempt
+    #[inline]
+    pub fn backoff(&self) -> Duration {
+        seconds(self.backoff)
+    }
+    /// Maximum Delay between Failed Attempts
+    #[inline]
+    pub fn backoff_max(&self) -> Duration {
+        seconds(self.backoff_max)
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            service: "dynlock".to_owned(),
+            backoff: 1.0,
+            backoff_max: 30.0,
+        }
+    }
+}
+
+/// Lock/Unlock Transition Configuration Settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnimationConfig {
+    /// Duration in seconds of the fade-in when locking
+    pub fade_in: f32,
+    /// Duration in seconds of the fade-out before unlocking
+    pub fade_out: f32,
+}
+
+impl AnimationConfig {
+    /// Fade-In Duration (Negative Values Disable the Animation)
+    #[inline]
+    pub fn fade_in(&self) -> Duration {
+        seconds(self.fade_in)
+    }
+    /// Fade-Out Duration (Negative Values Disable the Animation)
+    #[inline]
+    pub fn fade_out(&self) -> Duration {
+        seconds(self.fade_out)
+    }
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            fade_in: 0.5,
+            fade_out: 0.5,
+        }
+    }
+}
+
+/// Backend used to Draw Lock Surfaces
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RendererBackend {
+    /// Render shaders with wgpu and fallback to software without a usable GPU
+    #[default]
+    Auto,
+    /// Always draw into shared memory buffers on the CPU (no shaders)
+    Software,
+}
+
+/// Rendering Backend Configuration Settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RendererConfig {
+    /// Rendering backend selection
+    pub backend: RendererBackend,
+    /// Blur radius in pixels of the background drawn by the software renderer
+    pub blur: f32,
+    /// Darkening (0-1) of the background drawn by the software renderer
+    pub dim: f32,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            backend: RendererBackend::Auto,
+            blur: 8.0,
+            dim: 0.3,
+        }
+    }
+}
+
+/// Order in which Playlist Entries are Chosen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistOrder {
+    Ordered,
+    #[default]
+    Shuffle,
+}
+
+/// Power Source Condition of a Playlist Rule
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerCondition {
+    #[default]
+    Always,
+    OnBattery,
+    OnAc,
+}
+
+/// Weighting Rule Applied to Matching Playlist Shaders
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistRule {
+    /// Shader names the rule applies to
+    pub shaders: Vec<String>,
+    /// Power source required for the rule to apply
+    #[serde(default)]
+    pub when: PowerCondition,
+    /// Selection weight multiplier (0 excludes the shaders)
+    pub weight: f32,
+}
+
+/// Shader and Background Playlist Configuration Settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PlaylistConfig {
+    /// Shader files, packs or directories to choose from
+    pub shaders: Vec<String>,
+    /// Background images or directories to choose from
+    pub backgrounds: Vec<String>,
+    /// Order entries are rotated in
+    pub order: PlaylistOrder,
+    /// Minutes between rotations while locked (0 disables rotation)
+    pub interval: f32,
+    /// Duration in seconds of the crossfade between rotated shaders
+    pub crossfade: f32,
+    /// Shaders pinned to outputs by output name
+    pub outputs: BTreeMap<String, String>,
+    /// Shader exclusion and weighting rules
+    pub rules: Vec<PlaylistRule>,
+    /// Number of previous picks (across runs) not to repeat when shuffling
+    pub avoid_recent: usize,
+}
+
+impl PlaylistConfig {
+    /// Rotation Interval (None if Rotation is Disabled)
+    #[inline]
+    pub fn interval(&self) -> Option<Duration> {
+        match self.interval > 0.0 {
+            true => Some(seconds(self.interval * 60.0)),
+            false => None,
+        }
+    }
+    /// Crossfade Duration (Negative Values Disable the Animation)
+    #[inline]
+    pub fn crossfade(&self) -> Duration {
+        seconds(self.crossfade)
+    }
+}
+
+impl Default for PlaylistConfig {
+    fn default() -> Self {
+        Self {
+            shaders: vec![],
+            backgrounds: vec![],
+            order: PlaylistOrder::default(),
+            interval: 0.0,
+            crossfade: 2.0,
+            outputs: BTreeMap::new(),
+            rules: vec![],
+            avoid_recent: 1,
+        }
+    }
+}
+
+/// Idle Daemon Configuration Settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// Seconds of inactivity before the screensaver starts (0 disables)
+    pub screensaver: f32,
+    /// Seconds of inactivity before the session is locked (0 disables)
+    pub lock: f32,
+}
+
+impl IdleConfig {
+    /// Inactivity before the Screensaver Starts (None if Disabled)
+    #[inline]
+    pub fn screensaver(&self) -> Option<Duration> {
+        match self.screensaver > 0.0 {
+            true => Some(seconds(self.screensaver)),
+            false => None,
+        }
+    }
+    /// Inactivity before the Session is Locked (None if Disabled)
+    #[inline]
+    pub fn lock(&self) -> Option<Duration> {
+        match self.lock > 0.0 {
+            true => Some(seconds(self.lock)),
+            false => None,
+        }
+    }
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            screensaver: 300.0,
+            lock: 600.0,
+        }
+    }
+}
+
+/// Shader Parameter Value
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Vector(Vec<f32>),
+}
+
+/// Shader Texture Input Source
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureSource {
+    /// Image file (relative to the shader manifest)
+    Image(PathBuf),
+    /// Configured desktop wallpaper image
+    Wallpaper,
+    /// Avatar of the locked user (cropped into a circle)
+    Avatar,
+}
+
+/// RGBA Color Parsed from Hex Notation (`#rrggbb` or `#rrggbbaa`)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgba(pub [f32; 4]);
+
+impl Rgba {
+    /// Build Color from 8-bit Channels
+    pub const fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self([
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        ])
+    }
+}
+
+impl TryFrom<String> for Rgba {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value.trim_start_matches('#');
+        let channel = |n: usize| {
+            hex.get(n * 2..n * 2 + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| format!("invalid color: {value:?}"))
+        };
+        match hex.len() {
+            6 => Ok(Self::rgba8(channel(0)?, channel(1)?, channel(2)?, 255)),
+            8 => Ok(Self::rgba8(
+                channel(0)?,
+                channel(1)?,
+                channel(2)?,
+                channel(3)?,
+            )),
+            _ => Err(format!("invalid color: {value:?} (expected #rrggbb[aa])")),
+        }
+    }
+}
+
+/// Screen Position the Lock Menu is Anchored to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    #[default]
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Lock Menu Color Palette
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ThemeColors {
+    /// Text and icon color
+    pub text: Rgba,
+    /// Menu box background
+    pub background: Rgba,
+    /// Text and border color after a failed attempt
+    pub wrong: Rgba,
+    /// Menu box background after a failed attempt
+    pub wrong_background: Rgba,
+    /// Password input background
+    pub input: Rgba,
+    /// Menu box and password input border
+    pub border: Rgba,
+    /// Selected password text highlight
+    pub selection: Rgba,
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self {
+            text: Rgba::rgba8(255, 255, 255, 255),
+            background: Rgba::rgba8(1, 4, 11, 179),
+            wrong: Rgba::rgba8(230, 64, 64, 255),
+            wrong_background: Rgba::rgba8(40, 4, 11, 179),
+            input: Rgba::rgba8(0, 0, 0, 0),
+            border: Rgba::rgba8(0, 0, 0, 0),
+            selection: Rgba::rgba8(255, 255, 255, 255),
+        }
+    }
+}
+
+/// Lock Menu Appearance Settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub colors: ThemeColors,
+    /// Avatar image (defaults to the AccountsService icon or `~/.face`)
+    pub avatar: Option<String>,
+    /// Font family name used for all text
+    pub font: Option<String>,
+    /// Font file (ttf/otf) loaded before the font family is resolved
+    pub font_file: Option<String>,
+    /// Size of status and password text
+    pub font_size: f32,
+    /// Size of the clock text
+    pub clock_size: f32,
+    /// Size of the caps-lock and show/hide icons
+    pub icon_size: f32,
+    /// Diameter of the user avatar
+    pub avatar_size: f32,
+    /// Width of the password input
+    pub input_width: f32,
+    /// Padding within the menu box
+    pub padding: f32,
+    /// Distance between the menu box and the screen edge
+    pub margin: f32,
+    /// Width of the menu box and password input borders
+    pub border_width: f32,
+    /// Corner radius of the menu box and password input
+    pub radius: f32,
+    /// Screen position of the menu box (overrides the layout's anchor)
+    pub anchor: Option<Anchor>,
+    /// Horizontal/vertical offset of the menu box from its anchor
+    pub offset: [f32; 2],
+    /// Clock format string (strftime syntax)
+    pub clock_format: String,
+    /// Date format string (strftime syntax)
+    pub date_format: String,
+    /// Password input placeholder text
+    pub placeholder: String,
+    /// Name of the layout used (builtin or custom)
+    pub layout: String,
+    /// Custom layouts keyed by name
+    pub layouts: BTreeMap<String, LayoutConfig>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            colors: ThemeColors::default(),
+            avatar: None,
+            font: None,
+            font_file: None,
+            font_size: 12.0,
+            clock_size: 32.0,
+            icon_size: 15.0,
+            avatar_size: 96.0,
+            input_width: 300.0,
+            padding: 10.0,
+            margin: 25.0,
+            border_width: 0.0,
+            radius: 0.0,
+            anchor: None,
+            offset: [0.0, 0.0],
+            clock_format: "%H:%M:%S".to_owned(),
+            date_format: "%A, %B %-d".to_owned(),
+            placeholder: "Type password to unlock...".to_owned(),
+            layout: "classic".to_owned(),
+            layouts: BTreeMap::new(),
+        }
+    }
+}
+
+/// Lock Menu Widget Placed within a Layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Widget {
+    /// Current time
+    Clock,
+    /// Current date
+    Date,
+    /// Full name of the locked user
+    User,
+    /// Avatar of the locked user
+    Avatar,
+    /// Password input
+    Password,
+    /// Row of dots for every typed character (hides the password input)
+    Dots,
+    /// Caps-lock indicator
+    Caps,
+    /// Show/hide password toggle
+    Toggle,
+    /// Authentication status and feedback messages
+    Status,
+}
+
+/// Horizontal Alignment of Layout Rows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+/// Lock Menu Layout Composed of Rows of Widgets
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// Rows of widgets from top to bottom
+    pub rows: Vec<Vec<Widget>>,
+    /// Default screen position of the menu
+    pub anchor: Anchor,
+    /// Horizontal alignment of the rows
+    pub align: Align,
+    /// Draw the menu box background
+    pub boxed: bool,
+    /// Clock size multiplier
+    pub clock_scale: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl LayoutConfig {
+    /// Clock, Password and Status in the Bottom-Left Corner
+    pub fn classic() -> Self {
+        Self {
+            rows: vec![
+                vec![Widget::Clock],
+                vec![Widget::Password, Widget::Caps, Widget::Toggle],
+                vec![Widget::Status],
+            ],
+            anchor: Anchor::BottomLeft,
+            align: Align::Start,
+            boxed: true,
+            clock_scale: 1.0,
+        }
+    }
+
+    /// Retrieve Builtin Layout by Name
+    pub fn builtin(name: &str) -> Option<Self> {
+        let classic = Self::classic();
+        let form = vec![Widget::Password, Widget::Caps, Widget::Toggle];
+        match name {
+            "classic" => Some(classic),
+            "card" => Some(Self {
+                rows: vec![
+                    vec![Widget::Clock],
+                    vec![Widget::Date],
+                    form,
+                    vec![Widget::Status],
+                ],
+                anchor: Anchor::Center,
+                align: Align::Center,
+                ..classic
+            }),
+            "minimal" => Some(Self {
+                rows: vec![vec![Widget::Dots, Widget::Caps]],
+                anchor: Anchor::Center,
+                align: Align::Center,
+                boxed: false,
+                ..classic
+            }),
+            "clock" => Some(Self {
+                rows: vec![
+                    vec![Widget::Clock],
+                    vec![Widget::Date],
+                    form,
+                    vec![Widget::Status],
+                ],
+                anchor: Anchor::Center,
+                align: Align::Center,
+                boxed: false,
+                clock_scale: 3.0,
+            }),
+            "user" => Some(Self {
+                rows: vec![
+                    vec![Widget::Avatar],
+                    vec![Widget::User],
+                    form,
+                    vec![Widget::Status],
+                ],
+                anchor: Anchor::Center,
+                align: Align::Center,
+                ..classic
+            }),
+            _ => None,
         }
     }
 }
@@ -31,6 +635,60 @@ impl Default for Config {
 /// Lockscreen Configuration Settings
 pub struct Settings {
     pub lock: bool,
-    pub shader: String,
+    pub grace: Option<Duration>,
+    pub shader_path: PathBuf,
     pub background: Option<PathBuf>,
+    pub wallpaper: Option<PathBuf>,
+    pub avatar: Option<PathBuf>,
+    pub max_fps: Option<u32>,
+    pub watch: bool,
+    pub auth: AuthConfig,
+    pub animation: AnimationConfig,
+    pub renderer: RendererConfig,
+    pub params: BTreeMap<String, BTreeMap<String, ParamValue>>,
+    pub textures: BTreeMap<String, Vec<TextureSource>>,
+    pub playlist: PlaylistConfig,
+    pub theme: ThemeConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_accepts_seconds() {
+        assert_eq!(duration("test", 0.0), Ok(Duration::ZERO));
+        assert_eq!(duration("test", 1.5), Ok(Duration::from_millis(1500)));
+        assert_eq!(
+            duration("test", MAX_SECONDS),
+            Ok(Duration::from_secs(MAX_SECONDS as u64))
+        );
+    }
+
+    #[test]
+    fn duration_rejects_invalid_seconds() {
+        for value in [-1.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY, f32::MAX] {
+            assert!(duration("test", value).is_err(), "accepted {value}");
+        }
+    }
+
+    #[test]
+    fn validate_rejects_invalid_fields() {
+        assert!(Config::default().validate().is_ok());
+        let mut config = Config::default();
+        config.auth.backoff = f32::NAN;
+        assert!(config.validate().unwrap_err().contains("auth.backoff"));
+        let mut config = Config::default();
+        config.idle.lock = -5.0;
+        assert!(config.validate().unwrap_err().contains("idle.lock"));
+        let mut config = Config::default();
+        config.animation.fade_in = -1.0;
+        config.playlist.crossfade = -1.0;
+        assert!(config.validate().is_ok());
+        config.animation.fade_out = f32::NAN;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .contains("animation.fade_out"));
+    }
 }
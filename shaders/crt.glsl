@@ -9,10 +9,23 @@ layout(location=0) out vec4 f_color;
 layout(set = 0, binding = 0) uniform texture2D t_screenshot;
 layout(set = 0, binding = 1) uniform sampler s_screenshot;
 
-layout(push_constant) uniform FrameUniforms {
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
     float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
     float iFadeAmount;
-    vec2  iResolution;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
 };
 
 const float BULGE_AMOUNT = 0.2;
@@ -93,5 +106,6 @@ void main() {
     c = scan_lines(c, uv_monitor);
 
     f_color = c;
-    f_color = mix(f_color, vec4(0.0, 0.0, 0.0, 1.0), iFadeAmount);
+    // fade towards the desktop while locking/unlocking
+    f_color = mix(f_color, texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy), iFadeAmount);
 }
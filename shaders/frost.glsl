@@ -9,25 +9,43 @@ layout(location=0) out vec4 f_color;
 layout(set = 0, binding = 0) uniform texture2D t_screenshot;
 layout(set = 0, binding = 1) uniform sampler s_screenshot;
 
-layout(push_constant) uniform FrameUniforms {
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
     float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
     float iFadeAmount;
-    vec2  iResolution;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
 };
 
-const float AMOUNT = 4.0;
-const float SPEED  = 0.1;
-const float ACCEL  = 1.8;
+// tunable parameters declared in frost.yaml
+layout(set = 0, binding = 7) uniform Params {
+    float AMOUNT;
+    float SPEED;
+    float ACCEL;
+    float SIZE;
+};
 
 const float DIRECTIONS = 16.0;
 const float QUALITY = 3.0;
-const float SIZE = 8.0;
 
 void main()
 {
     float Pi = 6.28318530718; // Pi*2
 
     float tmod   = AMOUNT * clamp(pow(SPEED * iTime, ACCEL), 0.0, 1.0);
+    // thaw the frost while unlocking and flicker it on keypress
+    tmod *= (1.0 - iUnlockProgress) * (1.0 + 0.25*exp(-6.0*iKeystrokeTime));
     vec2  radius = (SIZE / iResolution.xy) * tmod;
     vec2  uv     = gl_FragCoord.xy / iResolution.xy;
     vec4  color  = texture(sampler2D(t_screenshot, s_screenshot), uv);
@@ -43,5 +61,8 @@ void main()
 
     // Output to screen
     color /= QUALITY * DIRECTIONS - 15.0;
+    color.rgb = mix(color.rgb, vec3(0.9, 0.25, 0.25), 0.35*iFailurePulse);
     f_color =  color;
+    // fade towards the desktop while locking/unlocking
+    f_color = mix(f_color, texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy), iFadeAmount);
 }
@@ -9,10 +9,23 @@ layout(location=0) out vec4 f_color;
 layout(set = 0, binding = 0) uniform texture2D t_screenshot;
 layout(set = 0, binding = 1) uniform sampler s_screenshot;
 
-layout(push_constant) uniform FrameUniforms {
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
     float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
     float iFadeAmount;
-    vec2  iResolution;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
 };
 
 void main()
@@ -30,11 +43,7 @@ void main()
     // pick a color using the turbulent coordinates
     vec2 wave  = sin(p.xy*.3+vec2(0,1))*.5+.5;
     vec3 color = texture(sampler2D(t_screenshot, s_screenshot), wave.xy).rgb;
-    // mix with base image for first second
-    if (iTime < 1.) {
-      vec2 uv   = gl_FragCoord.xy / iResolution.xy;
-      vec3 base = texture(sampler2D(t_screenshot, s_screenshot), uv.xy).rgb;
-      color = mix(base, color, iTime);
-    }
     f_color  = vec4(color, 1.0);
+    // fade towards the desktop while locking/unlocking
+    f_color = mix(f_color, texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy), iFadeAmount);
 }
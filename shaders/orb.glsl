@@ -9,10 +9,23 @@ layout(location=0) out vec4 f_color;
 layout(set = 0, binding = 0) uniform texture2D t_screenshot;
 layout(set = 0, binding = 1) uniform sampler s_screenshot;
 
-layout(push_constant) uniform FrameUniforms {
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
     float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
     float iFadeAmount;
-    vec2  iResolution;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
 };
 
 // Tinker with these parameters to create different solids
@@ -59,28 +72,29 @@ vec3 hsv2rgb(vec3 c) {
 #define NORM_OFF3           0.005
 
 const vec3 rayOrigin    = vec3(0.0, 1., -5.);
-const vec3 sunDir       = normalize(-rayOrigin);
+//  Expanded where used since builtin calls are not constant expressions for naga
+#define sunDir          normalize(-rayOrigin)
 
 
-const vec3 sunCol       = HSV2RGB(vec3(0.06 , 0.90, 1E-2))*1.;
-const vec3 bottomBoxCol = HSV2RGB(vec3(0.66, 0.80, 0.5))*1.;
-const vec3 topBoxCol    = HSV2RGB(vec3(0.60, 0.90, 1.))*1.;
-const vec3 glowCol0     = HSV2RGB(vec3(0.05 , 0.7, 1E-3))*1.;
-const vec3 glowCol1     = HSV2RGB(vec3(0.95, 0.7, 1E-3))*1.;
-const vec3 beerCol      = -HSV2RGB(vec3(0.15+0.5, 0.7, 2.)); 
+#define sunCol          (HSV2RGB(vec3(0.06 , 0.90, 1E-2))*1.)
+#define bottomBoxCol    (HSV2RGB(vec3(0.66, 0.80, 0.5))*1.)
+#define topBoxCol       (HSV2RGB(vec3(0.60, 0.90, 1.))*1.)
+#define glowCol0        (HSV2RGB(vec3(0.05 , 0.7, 1E-3))*1.)
+#define glowCol1        (HSV2RGB(vec3(0.95, 0.7, 1E-3))*1.)
+#define beerCol         (-HSV2RGB(vec3(0.15+0.5, 0.7, 2.)))
 const float rrefr_index = 1./refr_index;
 
 
 // License: Unknown, author: knighty, found: https://www.shadertoy.com/view/MsKGzw
-const float poly_cospin   = cos(PI/float(poly_type));
-const float poly_scospin  = sqrt(0.75-poly_cospin*poly_cospin);
-const vec3  poly_nc       = vec3(-0.5, -poly_cospin, poly_scospin);
 const vec3  poly_pab      = vec3(0., 0., 1.);
-const vec3  poly_pbc_     = vec3(poly_scospin, 0., 0.5);
-const vec3  poly_pca_     = vec3(0., poly_scospin, poly_cospin);
-const vec3  poly_p        = normalize((poly_U*poly_pab+poly_V*poly_pbc_+poly_W*poly_pca_));
-const vec3  poly_pbc      = normalize(poly_pbc_);
-const vec3  poly_pca      = normalize(poly_pca_);
+#define     poly_cospin   cos(PI/float(poly_type))
+#define     poly_scospin  sqrt(0.75-poly_cospin*poly_cospin)
+#define     poly_nc       vec3(-0.5, -poly_cospin, poly_scospin)
+#define     poly_pbc_     vec3(poly_scospin, 0., 0.5)
+#define     poly_pca_     vec3(0., poly_scospin, poly_cospin)
+#define     poly_p        normalize((poly_U*poly_pab+poly_V*poly_pbc_+poly_W*poly_pca_))
+#define     poly_pbc      normalize(poly_pbc_)
+#define     poly_pca      normalize(poly_pca_)
 
 mat3 g_rot;
 vec2 g_gd;
@@ -369,5 +383,11 @@ void main() {
   g_rot = rot;
 
   vec3 col = effect(p, pp);
+  // glow on keypress, flash red on failure and brighten while unlocking
+  col *= 1.0 + 0.3*exp(-4.0*iKeystrokeTime);
+  col = mix(col, vec3(0.9, 0.2, 0.2)*length(col), 0.6*iFailurePulse);
+  col = mix(col, vec3(1.0), iUnlockProgress*iUnlockProgress);
   f_color = vec4(col, 1.0);
+  // fade towards the desktop while locking/unlocking
+  f_color = mix(f_color, texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy), iFadeAmount);
 }
@@ -9,10 +9,23 @@ layout(location=0) out vec4 f_color;
 layout(set = 0, binding = 0) uniform texture2D t_screenshot;
 layout(set = 0, binding = 1) uniform sampler s_screenshot;
 
-layout(push_constant) uniform FrameUniforms {
+layout(set = 0, binding = 2) uniform FrameUniforms {
+    vec3  iResolution;
     float iTime;
+    float iTimeDelta;
+    int   iFrame;
+    float iFrameRate;
     float iFadeAmount;
-    vec2  iResolution;
+    vec4  iMouse;
+    vec4  iDate;
+    float iFailedAttempts;
+    float iAuthenticating;
+    float iPasswordLength;
+    float iKeystrokeTime;
+    float iCapsLock;
+    float iFailurePulse;
+    float iUnlockProgress;
+    float iBufferPass;
 };
 
 float SPEED = iTime * 0.4;
@@ -20,7 +33,7 @@ float SPEED = iTime * 0.4;
 #define lofi(i,j) (floor((i)/(j))*(j))
 #define lofir(i,j) (round((i)/(j))*(j))
 
-const float PI=acos(-1.);
+const float PI=3.141592654;
 const float TAU=PI*2.;
 
 mat2 r2d(float t){
@@ -499,4 +512,6 @@ void main ()
   col=pow(col,vec3(.4545));
   col=smoothstep(vec3(0,-.1,-.2),vec3(1,1.1,1.2),col);
   f_color = vec4(col,0);
+  // fade towards the desktop while locking/unlocking
+  f_color = mix(f_color, texture(sampler2D(t_screenshot, s_screenshot), gl_FragCoord.xy / iResolution.xy), iFadeAmount);
 }
@@ -0,0 +1,56 @@
+// Buffer A: particles drawn on top of the previous frame, which slowly
+// decays so older positions leave a fading trail behind them.
+
+struct FrameUniforms {
+    iResolution: vec3<f32>,
+    iTime: f32,
+    iTimeDelta: f32,
+    iFrame: i32,
+    iFrameRate: f32,
+    iFadeAmount: f32,
+    iMouse: vec4<f32>,
+    iDate: vec4<f32>,
+    iFailedAttempts: f32,
+    iAuthenticating: f32,
+    iPasswordLength: f32,
+    iKeystrokeTime: f32,
+    iCapsLock: f32,
+    iFailurePulse: f32,
+    iUnlockProgress: f32,
+    iBufferPass: f32,
+}
+
+@group(0) @binding(1) var s_screenshot: sampler;
+@group(0) @binding(2) var<uniform> u: FrameUniforms;
+@group(0) @binding(3) var t_channel0: texture_2d<f32>;
+
+struct Params {
+    particles: i32,
+    decay: f32,
+}
+
+@group(0) @binding(7) var<uniform> params: Params;
+
+@fragment
+fn main(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = coord.xy / u.iResolution.xy;
+    let aspect = u.iResolution.x / u.iResolution.y;
+    // decay the previous frame independent of the frame rate
+    let decay = pow(params.decay, u.iTimeDelta);
+    var color = textureSample(t_channel0, s_screenshot, uv).rgb * decay;
+    for (var i = 0; i < params.particles; i++) {
+        let n = f32(i);
+        let t = u.iTime * (0.4 + 0.1 * n) + n * 1.3;
+        let center = vec2<f32>(0.5 + 0.35 * cos(t * 1.3), 0.5 + 0.35 * sin(t * 1.7));
+        let delta = (uv - center) * vec2<f32>(aspect, 1.0);
+        let glow = min(0.0004 / max(dot(delta, delta), 0.0001), 1.0);
+        let hue = vec3<f32>(0.5) + 0.5 * cos(vec3<f32>(0.0, 2.0, 4.0) + n);
+        color += hue * glow;
+    }
+    // keypresses brighten the particles, failed attempts tint them red
+    let keypress = exp(-u.iKeystrokeTime * 4.0);
+    color *= 1.0 + keypress * 0.5;
+    let red = vec3<f32>(dot(color, vec3<f32>(0.4)), 0.0, 0.0);
+    color = mix(color, red, u.iFailurePulse * 0.6);
+    return vec4<f32>(min(color, vec3<f32>(8.0)), 1.0);
+}
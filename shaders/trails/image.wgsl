@@ -0,0 +1,38 @@
+// Image: accumulated particle trails composited over the dimmed wallpaper.
+
+struct FrameUniforms {
+    iResolution: vec3<f32>,
+    iTime: f32,
+    iTimeDelta: f32,
+    iFrame: i32,
+    iFrameRate: f32,
+    iFadeAmount: f32,
+    iMouse: vec4<f32>,
+    iDate: vec4<f32>,
+    iFailedAttempts: f32,
+    iAuthenticating: f32,
+    iPasswordLength: f32,
+    iKeystrokeTime: f32,
+    iCapsLock: f32,
+    iFailurePulse: f32,
+    iUnlockProgress: f32,
+    iBufferPass: f32,
+}
+
+@group(0) @binding(0) var t_screenshot: texture_2d<f32>;
+@group(0) @binding(1) var s_screenshot: sampler;
+@group(0) @binding(2) var<uniform> u: FrameUniforms;
+@group(0) @binding(3) var t_channel0: texture_2d<f32>;
+@group(0) @binding(8) var t_texture0: texture_2d<f32>;
+
+@fragment
+fn main(@builtin(position) coord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = coord.xy / u.iResolution.xy;
+    let desktop = textureSample(t_screenshot, s_screenshot, uv);
+    let wallpaper = textureSample(t_texture0, s_screenshot, uv);
+    let trails = textureSample(t_channel0, s_screenshot, uv).rgb;
+    // tonemap the unbounded trail intensity before blending
+    let color = wallpaper.rgb * 0.25 + trails / (vec3<f32>(1.0) + trails);
+    // fade towards the desktop while locking/unlocking
+    return mix(vec4<f32>(color, 1.0), desktop, u.iFadeAmount);
+}